@@ -0,0 +1,3668 @@
+//! Code generation for the [build-it](https://docs.rs/build-it) builder derive.
+//!
+//! This crate contains the expansion logic behind `#[derive(Builder)]` as a regular library, so
+//! other procedural macros can generate builder methods for the structs they emit without asking
+//! their users to add a second derive.
+//!
+//! # Example
+//!
+//! ```
+//! let input: syn::DeriveInput = syn::parse_quote! {
+//!     #[derive(Default)]
+//!     struct MyAwesomeStruct {
+//!         name: Option<String>,
+//!     }
+//! };
+//! let tokens = build_it_core::expand(&input).unwrap();
+//! assert!(tokens.to_string().contains("pub fn name"));
+//! ```
+//!
+//! Inside a derive macro, the error can be turned into a compile error:
+//! ```ignore
+//! #[proc_macro_derive(MyDerive, attributes(build_it))]
+//! pub fn my_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+//!     let input = syn::parse_macro_input!(input as syn::DeriveInput);
+//!     build_it_core::expand(&input)
+//!         .unwrap_or_else(syn::Error::into_compile_error)
+//!         .into()
+//! }
+//! ```
+
+use std::collections::HashSet;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, DeriveInput};
+
+/// Generate the builder implementation for a struct, as `#[derive(Builder)]` does.
+///
+/// The input is the parsed item the derive is applied to, including its `#[build_it(...)]`
+/// attributes. Returns an error if the item isn't a struct with named fields, or if one of the
+/// attributes is invalid.
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let global_attr = parse_global_attr(input)?;
+    let data = match input.data {
+        syn::Data::Struct(ref data) => data,
+        syn::Data::Enum(ref data) => {
+            return Err(syn::Error::new(
+                data.enum_token.span(),
+                "Builder derive does not work on enums",
+            ))
+        }
+        syn::Data::Union(ref data) => {
+            return Err(syn::Error::new(
+                data.union_token.span(),
+                "Builder derive does not work on unions",
+            ))
+        }
+    };
+
+    let fields = match data.fields {
+        syn::Fields::Named(ref fields) => &fields.named,
+        syn::Fields::Unit => return Ok(quote! {}),
+        syn::Fields::Unnamed(ref fields) => {
+            return Err(syn::Error::new(
+                fields.span(),
+                "Builder derive only works on structs with named fields",
+            ))
+        }
+    };
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let mut attr = parse_attr(field)?;
+            if attr.vis.is_none() {
+                attr.vis.clone_from(&global_attr.vis);
+            }
+            if attr.rename.is_none()
+                && (global_attr.rename_all.is_some() || global_attr.suffix.is_some())
+            {
+                let field_name = field.ident.as_ref().unwrap().to_string();
+                let field_name = match global_attr.rename_all {
+                    Some(case) => case.apply(&field_name),
+                    None => field_name,
+                };
+                let suffix = global_attr.suffix.as_deref().unwrap_or_default();
+                attr.rename = Some(format!("{}{}", field_name, suffix));
+            }
+            Ok(ParsedField { field, attr })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    if !global_attr.builder || global_attr.typestate {
+        if global_attr.validate.is_some() {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "`validate` requires `#[build_it(builder)]`",
+            ));
+        }
+        if let Some(field) = fields.iter().find(|field| field.attr.validate.is_some()) {
+            return Err(syn::Error::new(
+                field.field.span(),
+                "`validate` requires `#[build_it(builder)]`",
+            ));
+        }
+    }
+    if global_attr.typestate && global_attr.mutable {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "`mutable` cannot be used with `#[build_it(typestate)]`",
+        ));
+    }
+    if global_attr.typestate && global_attr.dual {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "`dual` cannot be used with `#[build_it(typestate)]`",
+        ));
+    }
+    let dual_mutable = fields
+        .iter()
+        .find(|field| (field.attr.dual || global_attr.dual) && field.is_mutable(&global_attr));
+    if let Some(field) = dual_mutable {
+        return Err(syn::Error::new(
+            field.field.span(),
+            "`dual` already generates a `set_*` method taking `&mut self`, it cannot be used with `mutable` or `by_ref`",
+        ));
+    }
+    // `impl Into<T>` for a type parameter `T` leaves the compiler no way to infer `T`.
+    let generic_into = buildable_fields(&fields).find(|(field, ty)| {
+        field.is_into(&global_attr)
+            && field.attr.with.is_none()
+            && field.attr.map.is_none()
+            && is_type_param(ty, &input.generics)
+    });
+    if let Some((field, ty)) = generic_into {
+        return Err(syn::Error::new(
+            field.field.span(),
+            format!(
+                "`into` can't be used on a field of generic type `{}`, as the type couldn't be inferred from the argument. Use `#[build_it(into = false)]` on this field",
+                type_name(ty)
+            ),
+        ));
+    }
+    if global_attr.typestate {
+        // The typestate builder only generates the setters of the fields, so options for the
+        // other methods and types of the default mode have nothing to apply to.
+        let unsupported = [
+            ("describe", global_attr.describe),
+            ("preset", !global_attr.presets.is_empty()),
+            ("counters", global_attr.counters),
+            ("reset", global_attr.reset),
+            ("merge_from", global_attr.merge_from),
+            ("from_str", global_attr.from_str),
+            ("shared", global_attr.shared),
+            ("gen_tests", global_attr.gen_tests),
+            ("with", global_attr.with),
+            ("template", global_attr.template),
+            ("into_parts", global_attr.into_parts),
+            ("metrics", global_attr.metrics),
+            ("set_json", global_attr.set_json),
+            ("to_json_partial", global_attr.to_json_partial),
+            ("clap", global_attr.clap),
+            ("doc_examples", global_attr.doc_examples),
+            ("maybe", global_attr.maybe),
+            ("take", global_attr.take),
+            ("clear", global_attr.clear),
+        ];
+        if let Some((option, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                format!("`{}` cannot be used with `#[build_it(typestate)]`", option),
+            ));
+        }
+        if let Some(field) = fields.iter().find(|field| field.attr.mutable == Some(true)) {
+            return Err(syn::Error::new(
+                field.field.span(),
+                "`by_ref` cannot be used with `#[build_it(typestate)]`",
+            ));
+        }
+        if let Some(field) = fields.iter().find(|field| field.attr.dual) {
+            return Err(syn::Error::new(
+                field.field.span(),
+                "`dual` cannot be used with `#[build_it(typestate)]`",
+            ));
+        }
+        for field in &fields {
+            let attr = &field.attr;
+            let unsupported = attr.extra_methods().into_iter().chain([
+                ("nullable", attr.nullable),
+                ("test_setter", attr.test_setter.is_some()),
+            ]);
+            if let Some((option, _)) = unsupported.into_iter().find(|(_, set)| *set) {
+                return Err(syn::Error::new(
+                    field.field.span(),
+                    format!("`{}` cannot be used with `#[build_it(typestate)]`", option),
+                ));
+            }
+        }
+    }
+    // Skipped fields have no slot in the builder that a test setter could set.
+    let builder_test_setter = fields
+        .iter()
+        .find(|field| global_attr.builder && field.attr.test_setter.is_some());
+    if let Some(field) = builder_test_setter {
+        return Err(syn::Error::new(
+            field.field.span(),
+            "`test_setter` cannot be used with `#[build_it(builder)]`, as skipped fields aren't part of the builder",
+        ));
+    }
+    if global_attr.shared {
+        // The shared handle only mirrors the setters, so the options for other builder methods
+        // would leave it with a different set of methods than the builder.
+        let unsupported = [
+            ("dual", global_attr.dual),
+            ("maybe", global_attr.maybe),
+            ("take", global_attr.take),
+            ("clear", global_attr.clear),
+        ];
+        if let Some((option, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                format!("`{}` cannot be used with `#[build_it(shared)]`", option),
+            ));
+        }
+        for field in buildable_fields(&fields).map(|(field, _)| field) {
+            let unsupported = field.attr.extra_methods();
+            if let Some((option, _)) = unsupported.iter().find(|(_, set)| *set) {
+                return Err(syn::Error::new(
+                    field.field.span(),
+                    format!("`{}` cannot be used with `#[build_it(shared)]`", option),
+                ));
+            }
+        }
+    }
+    if global_attr.typestate {
+        return Ok(generate_typestate_builder(input, &global_attr, &fields));
+    }
+    if global_attr.builder {
+        return generate_builder_struct(input, &global_attr, &fields);
+    }
+    generate_builder_impl(input, &global_attr, &fields)
+}
+
+/// Generate a separate `<Struct>Builder` type with a `build()` method, for
+/// `#[build_it(builder)]`.
+///
+/// The builder holds an `Option` for every field that isn't skipped, and gets the same builder
+/// methods as the struct would in the default mode. `build()` fails with a
+/// `<Struct>BuilderError` if a required field is unset, and fills skipped fields with their
+/// `Default` value.
+///
+/// # Example
+///
+/// For a struct `SimpleStruct` with fields `name: String` and `age: Option<u32>`, the
+/// generated code is:
+/// ```ignore
+/// pub struct SimpleStructBuilder {
+///     name: Option<String>,
+///     age: Option<u32>,
+///     __marker: PhantomData<fn() -> SimpleStruct>,
+/// }
+/// impl SimpleStruct {
+///     pub fn builder() -> SimpleStructBuilder {
+///         Default::default()
+///     }
+/// }
+/// impl SimpleStructBuilder {
+///     pub fn build(self) -> Result<SimpleStruct, SimpleStructBuilderError> {
+///         let mut missing = Vec::new();
+///         if self.name.is_none() {
+///             missing.push("name");
+///         }
+///         if !missing.is_empty() {
+///             return Err(SimpleStructBuilderError::MissingFields(missing));
+///         }
+///         Ok(SimpleStruct {
+///             name: self.name.expect("required fields are checked above"),
+///             age: self.age,
+///         })
+///     }
+///     // ... builder methods
+/// }
+/// pub enum SimpleStructBuilderError {
+///     MissingFields(Vec<&'static str>),
+/// }
+/// ```
+fn generate_builder_struct(
+    input: &DeriveInput,
+    global_attr: &GlobalAttr,
+    fields: &[ParsedField],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let fn_vis = global_attr.vis();
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let builder_name = syn::Ident::new(&format!("{}Builder", name), name.span());
+    let error_name = syn::Ident::new(&format!("{}BuilderError", name), name.span());
+
+    // The builder is expanded like a struct in the default mode, with every field wrapped in an
+    // `Option` that isn't one already.
+    let mut builder_input = input.clone();
+    builder_input.ident = builder_name.clone();
+    builder_input.data = syn::Data::Struct(syn::DataStruct {
+        struct_token: Default::default(),
+        fields: syn::Fields::Named(syn::FieldsNamed {
+            brace_token: Default::default(),
+            named: fields
+                .iter()
+                .filter(|field| !is_skipped(field))
+                .map(|field| {
+                    let mut builder_field = field.field.clone();
+                    if get_inner_type(&field.field.ty).is_none() {
+                        let ty = &field.field.ty;
+                        builder_field.ty = syn::parse_quote!(Option<#ty>);
+                    }
+                    builder_field
+                })
+                .collect(),
+        }),
+        semi_token: None,
+    });
+    let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(ref builder_fields),
+        ..
+    }) = builder_input.data
+    else {
+        unreachable!("the builder is a struct with named fields");
+    };
+    let builder_fields = builder_fields
+        .named
+        .iter()
+        .map(|field| {
+            let original = fields
+                .iter()
+                .find(|original| original.field.ident == field.ident)
+                .expect("builder fields come from the struct");
+            let mut attr = parse_attr(field)?;
+            attr.required = original.is_required();
+            attr.rename.clone_from(&original.attr.rename);
+            attr.vis.clone_from(&original.attr.vis);
+            Ok(ParsedField { field, attr })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let builder_impl = generate_builder_impl(&builder_input, global_attr, &builder_fields)?;
+    let shared = if global_attr.shared {
+        generate_shared(
+            &builder_input,
+            global_attr,
+            &builder_fields,
+            Some((name, &error_name)),
+        )
+    } else {
+        quote! {}
+    };
+    let from_str = if global_attr.from_str {
+        generate_from_str(&builder_input, global_attr, &builder_fields, Some(name))?
+    } else {
+        quote! {}
+    };
+    let deprecations = fields
+        .iter()
+        .filter_map(|field| generate_skip_deprecation(field.field));
+
+    // Only doc attributes are kept on the builder's fields, as other attributes may belong to
+    // derives that aren't applied to the builder.
+    let definitions = builder_fields.iter().map(|field| {
+        let field_name = field.field.ident.as_ref().unwrap();
+        let ty = &field.field.ty;
+        let docs = field
+            .field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"));
+        quote! {
+            #(#docs)*
+            #field_name: #ty,
+        }
+    });
+    let builder_field_names = builder_fields
+        .iter()
+        .map(|field| field.field.ident.as_ref().unwrap());
+    let values = fields.iter().map(|field| {
+        let field_name = field.field.ident.as_ref().unwrap();
+        let value = if is_skipped(field) {
+            quote! { core::default::Default::default() }
+        } else if field.is_required() {
+            field.store(quote! {
+                self.#field_name.expect("required fields are checked above")
+            })
+        } else if let Some(default) = field.default_value() {
+            field.store(quote! { self.#field_name.unwrap_or_else(|| #default) })
+        } else {
+            quote! { self.#field_name }
+        };
+        quote! { let #field_name = #value; }
+    });
+    let validations = fields.iter().filter_map(|field| {
+        let validate = field.attr.validate.as_ref()?;
+        let field_name = field.field.ident.as_ref().unwrap();
+        let key = field_name.to_string();
+        let check = quote! {
+            #validate(value).map_err(|err| #error_name::InvalidField {
+                field: #key,
+                source: core::convert::Into::into(err),
+            })?;
+        };
+        Some(if field.is_plain() {
+            quote! {
+                {
+                    let value = &#field_name;
+                    #check
+                }
+            }
+        } else {
+            quote! {
+                if let Some(value) = &#field_name {
+                    #check
+                }
+            }
+        })
+    });
+    let field_names = fields
+        .iter()
+        .map(|field| field.field.ident.as_ref().unwrap());
+    let validate = global_attr.validate.as_ref().map(|validate| {
+        quote! {
+            #validate(&value).map_err(|err| #error_name::Invalid(core::convert::Into::into(err)))?;
+        }
+    });
+    let required = fields
+        .iter()
+        .filter(|field| !is_skipped(field) && field.is_required())
+        .map(|field| field.field.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+    let check_required = (!required.is_empty()).then(|| {
+        let keys = required.iter().map(|field_name| field_name.to_string());
+        quote! {
+            let mut missing = ::std::vec::Vec::new();
+            #(
+                if self.#required.is_none() {
+                    missing.push(#keys);
+                }
+            )*
+            if !missing.is_empty() {
+                return Err(#error_name::MissingFields(missing));
+            }
+        }
+    });
+    // The template and shared modes need to clone the builder.
+    let derive_clone = if global_attr.template || global_attr.shared {
+        quote! { #[derive(Clone)] }
+    } else {
+        quote! {}
+    };
+    let doc = format!(" Builder for [`{}`].", name);
+    let builder_fn_doc = format!(" Creates a [`{}`] with no fields set.", builder_name);
+    let build_doc = format!(" Builds the [`{}`].", name);
+    let error_doc = format!(" Error returned by [`{}::build`].", builder_name);
+    let invalid_message = format!("invalid `{}`: {{}}", name);
+    let allow_case = global_attr.allow_case();
+    let build_generics = global_attr.bounded_generics(generics);
+    let (build_impl_generics, _, build_where_clause) = build_generics.split_for_impl();
+    Ok(quote! {
+        #[doc = #doc]
+        #derive_clone
+        #allow_case
+        #vis struct #builder_name #generics #where_clause {
+            #(#definitions)*
+            // Uses every generic parameter, even those only used by skipped fields.
+            __marker: core::marker::PhantomData<fn() -> #name #ty_generics>,
+        }
+
+        impl #impl_generics core::default::Default for #builder_name #ty_generics #where_clause {
+            fn default() -> Self {
+                Self {
+                    #(#builder_field_names: None,)*
+                    __marker: core::marker::PhantomData,
+                }
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc = #builder_fn_doc]
+            #fn_vis fn builder() -> #builder_name #ty_generics {
+                core::default::Default::default()
+            }
+        }
+
+        #allow_case
+        impl #build_impl_generics #builder_name #ty_generics #build_where_clause {
+            #[doc = #build_doc]
+            ///
+            /// Fails if a field that is required by the struct is not set, or if a value is
+            /// rejected by a validation function.
+            // Building the struct sets its `#[deprecated]` fields too.
+            #[allow(deprecated)]
+            #fn_vis fn build(self) -> ::core::result::Result<#name #ty_generics, #error_name> {
+                #check_required
+                #(#values)*
+                #(#validations)*
+                let value = #name {
+                    #(#field_names,)*
+                };
+                #validate
+                Ok(value)
+            }
+        }
+
+        #[doc = #error_doc]
+        #[derive(Debug)]
+        #vis enum #error_name {
+            /// Required fields were not set. Contains the name of every unset field, in
+            /// declaration order.
+            MissingFields(::std::vec::Vec<&'static str>),
+            /// The validation function of a field rejected its value.
+            InvalidField {
+                /// Name of the field.
+                field: &'static str,
+                /// Error returned by the validation function.
+                source: ::std::boxed::Box<dyn ::std::error::Error + Send + Sync>,
+            },
+            /// The validation function of the struct rejected the built value.
+            Invalid(::std::boxed::Box<dyn ::std::error::Error + Send + Sync>),
+        }
+
+        impl core::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                match self {
+                    Self::MissingFields(fields) => {
+                        for (i, field) in fields.iter().enumerate() {
+                            if i > 0 {
+                                f.write_str(", ")?;
+                            }
+                            write!(f, "`{}`", field)?;
+                        }
+                        if fields.len() == 1 {
+                            f.write_str(" is not set")
+                        } else {
+                            f.write_str(" are not set")
+                        }
+                    }
+                    Self::InvalidField { field, source } => {
+                        write!(f, "invalid value for `{}`: {}", field, source)
+                    }
+                    Self::Invalid(source) => write!(f, #invalid_message, source),
+                }
+            }
+        }
+
+        impl ::std::error::Error for #error_name {
+            fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+                match self {
+                    Self::MissingFields(_) => None,
+                    Self::InvalidField { source, .. } => Some(&**source),
+                    Self::Invalid(source) => Some(&**source),
+                }
+            }
+        }
+
+        #builder_impl
+        #shared
+
+        #from_str
+        #(#deprecations)*
+    })
+}
+
+/// Generate a `<Struct>Builder` type that checks at compile time that every required field is
+/// set, for `#[build_it(typestate)]`.
+///
+/// Fields that aren't an `Option` in the struct are required. The builder has a type parameter
+/// for each of them, which is `()` while the field is unset and `(T,)` once it is set, and
+/// `build()` is only implemented once all of them are set.
+///
+/// # Example
+///
+/// For a struct `SimpleStruct` with fields `name: String` and `age: Option<u32>`, the
+/// generated code is:
+/// ```ignore
+/// pub struct SimpleStructBuilder<__Name> {
+///     name: __Name,
+///     age: Option<u32>,
+///     __marker: PhantomData<fn() -> SimpleStruct>,
+/// }
+/// impl SimpleStruct {
+///     pub fn builder() -> SimpleStructBuilder<()> {
+///         SimpleStructBuilder { name: (), age: None, __marker: PhantomData }
+///     }
+/// }
+/// impl SimpleStructBuilder<()> {
+///     pub fn name(self, name: String) -> SimpleStructBuilder<(String,)> {
+///         SimpleStructBuilder { name: (name,), age: self.age, __marker: PhantomData }
+///     }
+/// }
+/// impl<__Name> SimpleStructBuilder<__Name> {
+///     pub fn age(mut self, age: u32) -> Self {
+///         self.age = Some(age);
+///         self
+///     }
+/// }
+/// impl SimpleStructBuilder<(String,)> {
+///     pub fn build(self) -> SimpleStruct {
+///         SimpleStruct { name: self.name.0, age: self.age }
+///     }
+/// }
+/// ```
+fn generate_typestate_builder(
+    input: &DeriveInput,
+    global_attr: &GlobalAttr,
+    fields: &[ParsedField],
+) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let fn_vis = global_attr.vis();
+    let must_use = global_attr.must_use();
+    let inline = global_attr.inline();
+    let builder_name = syn::Ident::new(&format!("{}Builder", name), name.span());
+    let generics = global_attr.bounded_generics(&input.generics);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let where_clause = where_clause.map(|clause| &clause.predicates);
+    let allow_case = global_attr.allow_case();
+
+    // The generic arguments of the struct, to be followed by the state of each required field.
+    let args = input
+        .generics
+        .params
+        .iter()
+        .map(|param| match param {
+            syn::GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote! { #lifetime }
+            }
+            syn::GenericParam::Type(param) => {
+                let ident = &param.ident;
+                quote! { #ident }
+            }
+            syn::GenericParam::Const(param) => {
+                let ident = &param.ident;
+                quote! { #ident }
+            }
+        })
+        .collect::<Vec<_>>();
+    // Defaults are left out, as they aren't allowed on the impls and the struct's parameters are
+    // followed by the states, which have no defaults.
+    let params = input
+        .generics
+        .params
+        .iter()
+        .cloned()
+        .map(|mut param| {
+            match &mut param {
+                syn::GenericParam::Type(param) => {
+                    param.eq_token = None;
+                    param.default = None;
+                }
+                syn::GenericParam::Const(param) => {
+                    param.eq_token = None;
+                    param.default = None;
+                }
+                syn::GenericParam::Lifetime(_) => {}
+            }
+            param
+        })
+        .collect::<Vec<_>>();
+
+    let stored = fields
+        .iter()
+        .filter(|field| !is_skipped(field))
+        .collect::<Vec<_>>();
+    let required = stored
+        .iter()
+        .filter(|field| field.is_required())
+        .map(|field| field.field)
+        .collect::<Vec<_>>();
+    // Fields like `a_b` and `ab_` share a state name, which may also be taken by one of the
+    // struct's own generics, so a numeric suffix is added until the name is free.
+    let mut taken = input
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            syn::GenericParam::Type(param) => Some(param.ident.to_string()),
+            syn::GenericParam::Const(param) => Some(param.ident.to_string()),
+            syn::GenericParam::Lifetime(_) => None,
+        })
+        .collect::<HashSet<_>>();
+    let states = required
+        .iter()
+        .map(|field| {
+            let field_name = field.ident.as_ref().unwrap().to_string();
+            let base = format!("__{}", Case::Pascal.apply(&field_name));
+            let mut state = base.clone();
+            let mut suffix = 1;
+            while !taken.insert(state.clone()) {
+                state = format!("{}{}", base, suffix);
+                suffix += 1;
+            }
+            syn::Ident::new(&state, Span::call_site())
+        })
+        .collect::<Vec<_>>();
+    let state_of = |field: &syn::Field| {
+        required
+            .iter()
+            .position(|required| required.ident == field.ident)
+    };
+
+    let definitions = stored.iter().map(|field| {
+        let field_name = field.field.ident.as_ref().unwrap();
+        let ty = match state_of(field.field) {
+            Some(index) => {
+                let state = &states[index];
+                quote! { #state }
+            }
+            None => {
+                let ty = get_inner_type(&field.field.ty).unwrap_or(&field.field.ty);
+                quote! { Option<#ty> }
+            }
+        };
+        quote! { #field_name: #ty, }
+    });
+    // The struct's generic parameters may only be used by required fields, whose types are
+    // replaced by their state.
+    let marker = quote! {
+        __marker: core::marker::PhantomData<fn() -> #name<#(#args),*>>,
+    };
+    let unset = stored.iter().map(|field| {
+        let field_name = field.field.ident.as_ref().unwrap();
+        match state_of(field.field) {
+            Some(_) => quote! { #field_name: () },
+            None => quote! { #field_name: None },
+        }
+    });
+    let unset_states = required.iter().map(|_| quote! { () });
+
+    let setters = stored.iter().map(|field| {
+        let fn_name = field.setter_name();
+        let vis = field.vis();
+        let docs = field.docs();
+        let doc_alias = field.doc_alias();
+        let forwarded = field.method_attrs(global_attr);
+        let bounds = field.bounds();
+        let field_name = field.field.ident.as_ref().unwrap();
+        let value_ty = get_inner_type(&field.field.ty).unwrap_or(&field.field.ty);
+        let param = field.param_name();
+        let (arg_ty, value) = field.argument(global_attr, value_ty);
+        let Some(index) = state_of(field.field) else {
+            return quote! {
+                #allow_case
+                impl<#(#params,)* #(#states),*> #builder_name<#(#args,)* #(#states),*>
+                where #where_clause
+                {
+                    #(#docs)*
+                    #doc_alias
+                    #must_use
+                    #forwarded
+                    #inline
+                    #vis fn #fn_name(mut self, #param: #arg_ty) -> Self
+                    #bounds
+                    {
+                        self.#field_name = Some(#value);
+                        self
+                    }
+                }
+            };
+        };
+        let other_states = states
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, state)| state);
+        let before = states.iter().enumerate().map(|(other, state)| {
+            if other == index {
+                quote! { () }
+            } else {
+                quote! { #state }
+            }
+        });
+        let after = states.iter().enumerate().map(|(other, state)| {
+            if other == index {
+                quote! { (#value_ty,) }
+            } else {
+                quote! { #state }
+            }
+        });
+        let moved = stored.iter().map(|other| {
+            let other_name = other.field.ident.as_ref().unwrap();
+            if other_name == field_name {
+                quote! { #other_name: (#value,) }
+            } else {
+                quote! { #other_name: self.#other_name }
+            }
+        });
+        quote! {
+            #allow_case
+            impl<#(#params,)* #(#other_states),*> #builder_name<#(#args,)* #(#before),*>
+            where #where_clause
+            {
+                #(#docs)*
+                #doc_alias
+                #must_use
+                #forwarded
+                #inline
+                #vis fn #fn_name(
+                    self,
+                    #param: #arg_ty,
+                ) -> #builder_name<#(#args,)* #(#after),*>
+                #bounds
+                {
+                    #builder_name {
+                        #(#moved,)*
+                        __marker: core::marker::PhantomData,
+                    }
+                }
+            }
+        }
+    });
+
+    let set_states = required.iter().map(|field| {
+        let ty = get_inner_type(&field.ty).unwrap_or(&field.ty);
+        quote! { (#ty,) }
+    });
+    let values = fields.iter().map(|field| {
+        let field_name = field.field.ident.as_ref().unwrap();
+        if is_skipped(field) {
+            quote! { #field_name: core::default::Default::default() }
+        } else if state_of(field.field).is_some() {
+            let value = field.store(quote! { self.#field_name.0 });
+            quote! { #field_name: #value }
+        } else if let Some(default) = field.default_value() {
+            let value = field.store(quote! { self.#field_name.unwrap_or_else(|| #default) });
+            quote! { #field_name: #value }
+        } else {
+            quote! { #field_name: self.#field_name }
+        }
+    });
+    let doc = format!(
+        " Builder for [`{}`], which checks at compile time that every required field is set.",
+        name
+    );
+    let builder_fn_doc = format!(" Creates a [`{}`] with no fields set.", builder_name);
+    let build_doc = format!(" Builds the [`{}`].", name);
+    let deprecations = fields
+        .iter()
+        .filter_map(|field| generate_skip_deprecation(field.field));
+    quote! {
+        #[doc = #doc]
+        #allow_case
+        #vis struct #builder_name<#(#params,)* #(#states),*>
+        where #where_clause
+        {
+            #(#definitions)*
+            #marker
+        }
+
+        impl #impl_generics #name #ty_generics where #where_clause {
+            #[doc = #builder_fn_doc]
+            #fn_vis fn builder() -> #builder_name<#(#args,)* #(#unset_states),*> {
+                #builder_name {
+                    #(#unset,)*
+                    __marker: core::marker::PhantomData,
+                }
+            }
+        }
+
+        #(#setters)*
+
+        impl #impl_generics #builder_name<#(#args,)* #(#set_states),*> where #where_clause {
+            #[doc = #build_doc]
+            #fn_vis fn build(self) -> #name #ty_generics {
+                #name {
+                    #(#values,)*
+                }
+            }
+        }
+
+        #(#deprecations)*
+    }
+}
+
+/// A naming convention that field names can be converted to, for `#[build_it(rename_all = "...")]`.
+#[derive(Clone, Copy)]
+enum Case {
+    Snake,
+    Camel,
+    Pascal,
+    ScreamingSnake,
+    Lower,
+    Upper,
+}
+
+impl Case {
+    fn parse(lit: &syn::LitStr) -> syn::Result<Self> {
+        match lit.value().as_str() {
+            "snake_case" => Ok(Self::Snake),
+            "camelCase" => Ok(Self::Camel),
+            "PascalCase" => Ok(Self::Pascal),
+            "SCREAMING_SNAKE_CASE" => Ok(Self::ScreamingSnake),
+            "lowercase" => Ok(Self::Lower),
+            "UPPERCASE" => Ok(Self::Upper),
+            _ => Err(syn::Error::new(
+                lit.span(),
+                "expected one of `snake_case`, `camelCase`, `PascalCase`, `SCREAMING_SNAKE_CASE`, `lowercase` or `UPPERCASE`",
+            )),
+        }
+    }
+
+    /// Convert a name in any of the supported conventions to this one.
+    fn apply(self, name: &str) -> String {
+        let words = split_words(name);
+        let capitalize = |word: &String| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        };
+        match self {
+            Self::Snake => words.join("_"),
+            Self::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    if i == 0 {
+                        word.clone()
+                    } else {
+                        capitalize(word)
+                    }
+                })
+                .collect(),
+            Self::Pascal => words.iter().map(capitalize).collect(),
+            Self::ScreamingSnake => words.join("_").to_uppercase(),
+            Self::Lower => words.concat(),
+            Self::Upper => words.concat().to_uppercase(),
+        }
+    }
+}
+
+/// Split a name into lowercase words, at underscores, where a lowercase letter or digit is
+/// followed by an uppercase letter, and before the last letter of a run of uppercase letters
+/// that is followed by a lowercase one, e.g. `maxRetryCount` into `max`, `retry` and `count`,
+/// and `HTTPProxy` into `http` and `proxy`.
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous: Option<char> = None;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        } else {
+            let next = chars.peek().copied();
+            let boundary = c.is_uppercase()
+                && previous.is_some_and(|previous| {
+                    previous.is_lowercase()
+                        || previous.is_numeric()
+                        || (previous.is_uppercase() && next.is_some_and(char::is_lowercase))
+                });
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.extend(c.to_lowercase());
+        }
+        previous = Some(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// A struct field along with its parsed `#[build_it(...)]` attribute.
+struct ParsedField<'a> {
+    field: &'a syn::Field,
+    attr: Attr,
+}
+
+impl ParsedField<'_> {
+    /// Name of the builder method, which is the field name unless it is renamed.
+    fn setter_name(&self) -> syn::Ident {
+        let field_name = self.field.ident.as_ref().unwrap();
+        syn::Ident::new(
+            self.attr
+                .rename
+                .as_deref()
+                .unwrap_or(&field_name.to_string()),
+            Span::call_site(),
+        )
+    }
+
+    /// Visibility of the builder methods, which are public unless `#[build_it(vis = "...")]` is
+    /// set.
+    fn vis(&self) -> syn::Visibility {
+        self.attr
+            .vis
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote! { pub })
+    }
+
+    /// Whether the builder methods accept any type that converts into the field type, which is
+    /// set by `#[build_it(into)]` on the field or the struct, unless the field opts out with
+    /// `#[build_it(into = false)]`. `Cow` fields always convert, so they can be set from borrowed
+    /// and owned values alike.
+    fn is_into(&self, global_attr: &GlobalAttr) -> bool {
+        let ty = get_inner_type(&self.field.ty).unwrap_or(&self.field.ty);
+        self.attr.into.unwrap_or(global_attr.into || is_cow(ty))
+    }
+
+    /// Name of the argument of the builder method, which is the field name. The name is hygienic,
+    /// so it doesn't shadow functions of the same name given to attributes like `with`.
+    fn param_name(&self) -> syn::Ident {
+        let field_name = self.field.ident.as_ref().unwrap();
+        syn::Ident::new(&field_name.to_string(), Span::mixed_site())
+    }
+
+    /// The argument type of the builder method, and the expression converting the argument, which
+    /// is named by `param_name()`, into a value of type `ty`.
+    fn argument(&self, global_attr: &GlobalAttr, ty: &syn::Type) -> (TokenStream, TokenStream) {
+        let field_name = self.param_name();
+        if let Some(with) = &self.attr.with {
+            let arg_ty = match &self.attr.arg {
+                Some(arg_ty) => quote! { #arg_ty },
+                None => quote! { #ty },
+            };
+            (arg_ty, quote! { #with(#field_name) })
+        } else if let Some(map) = &self.attr.map {
+            let arg_ty = match map.inputs.first() {
+                Some(syn::Pat::Type(pat)) => &pat.ty,
+                _ => unreachable!("`map` closures are checked when parsing"),
+            };
+            (quote! { #arg_ty }, quote! { (#map)(#field_name) })
+        } else if self.attr.to_string {
+            (
+                quote! { impl ::std::string::ToString },
+                quote! { ::std::string::ToString::to_string(&#field_name) },
+            )
+        } else if self.attr.boxed || is_boxed_callback(ty) {
+            let (arg_ty, constructor) =
+                boxed_argument(ty).expect("`boxed` fields are checked when parsing");
+            (arg_ty, quote! { #constructor(#field_name) })
+        } else if let Some(wrapper) = self.attr.shared_pointer() {
+            let inner = get_wrapped_type(ty, wrapper)
+                .expect("`arc` and `rc` fields are checked when parsing");
+            let path = match wrapper {
+                "Arc" => quote! { ::std::sync::Arc },
+                _ => quote! { ::std::rc::Rc },
+            };
+            if self.is_into(global_attr) {
+                (
+                    quote! { impl core::convert::Into<#inner> },
+                    quote! { #path::new(#field_name.into()) },
+                )
+            } else {
+                (quote! { #inner }, quote! { #path::new(#field_name) })
+            }
+        } else if self.attr.as_ref_path {
+            (
+                quote! { impl core::convert::AsRef<::std::path::Path> },
+                quote! { ::std::path::Path::to_path_buf(#field_name.as_ref()) },
+            )
+        } else if self.attr.as_ref_str {
+            (
+                quote! { impl core::convert::AsRef<str> },
+                quote! { ::std::string::String::from(#field_name.as_ref()) },
+            )
+        } else if self.attr.clone {
+            (
+                quote! { &#ty },
+                quote! { ::core::clone::Clone::clone(#field_name) },
+            )
+        } else if self.is_into(global_attr) {
+            (
+                quote! { impl core::convert::Into<#ty> },
+                quote! { #field_name.into() },
+            )
+        } else {
+            (quote! { #ty }, quote! { #field_name })
+        }
+    }
+
+    /// The argument type of the field's setter with the value it stores in the field, for a field
+    /// of type `Option<ty>`, or `ty` for a plain field.
+    fn setter_argument(
+        &self,
+        global_attr: &GlobalAttr,
+        ty: &syn::Type,
+    ) -> (TokenStream, TokenStream) {
+        // `nullable` fields are set from the value inside both options, so that `None` is left to
+        // mean "unset" and `Some(None)` can be set with the `null_*` method.
+        if self.attr.nullable {
+            let value_ty = get_inner_type(ty).expect("`nullable` fields are checked when parsing");
+            let (arg_ty, value) = self.argument(global_attr, value_ty);
+            (arg_ty, self.store(quote! { Some(#value) }))
+        } else {
+            let (arg_ty, value) = self.argument(global_attr, ty);
+            (arg_ty, self.store(value))
+        }
+    }
+
+    /// A type of value that the field's setter takes, for generated code calling the setter with
+    /// a value it parsed or created, e.g. `T` for an `Arc<T>` field. Boxed fields have none, as
+    /// their setter may take an `impl Trait`.
+    fn concrete_argument_type(&self, ty: &syn::Type) -> Option<TokenStream> {
+        if self.attr.boxed || is_boxed_callback(ty) {
+            return None;
+        }
+        // `nullable` setters take the value inside both options.
+        let ty = if self.attr.nullable {
+            get_inner_type(ty).expect("`nullable` fields are checked when parsing")
+        } else {
+            ty
+        };
+        Some(if self.attr.with.is_some() {
+            let arg_ty = self.attr.arg.as_ref().unwrap_or(ty);
+            quote! { #arg_ty }
+        } else if let Some(map) = &self.attr.map {
+            let arg_ty = match map.inputs.first() {
+                Some(syn::Pat::Type(pat)) => &pat.ty,
+                _ => unreachable!("`map` closures are checked when parsing"),
+            };
+            quote! { #arg_ty }
+        } else if let Some(wrapper) = self.attr.shared_pointer() {
+            let inner = get_wrapped_type(ty, wrapper)
+                .expect("`arc` and `rc` fields are checked when parsing");
+            quote! { #inner }
+        } else {
+            quote! { #ty }
+        })
+    }
+
+    /// A statement calling the field's setter on a `builder` variable with `value`, an expression
+    /// of its concrete argument type.
+    fn setter_call(&self, global_attr: &GlobalAttr, value: TokenStream) -> TokenStream {
+        let fn_name = self.setter_name();
+        let argument = if self.attr.clone {
+            quote! { &#value }
+        } else {
+            value
+        };
+        if self.is_mutable(global_attr) {
+            quote! { builder.#fn_name(#argument); }
+        } else {
+            quote! { builder = builder.#fn_name(#argument); }
+        }
+    }
+
+    /// Whether the builder methods take `&mut self` instead of consuming the builder, which is
+    /// set by `#[build_it(by_ref)]` or `#[build_it(by_value)]`, or `#[build_it(mutable)]` on the
+    /// struct.
+    fn is_mutable(&self, global_attr: &GlobalAttr) -> bool {
+        self.attr.mutable.unwrap_or(global_attr.mutable)
+    }
+
+    /// The receiver and return type of the builder methods.
+    fn receiver(&self, global_attr: &GlobalAttr) -> (TokenStream, TokenStream) {
+        if self.is_mutable(global_attr) {
+            (quote! { &mut self }, quote! { &mut Self })
+        } else {
+            (quote! { mut self }, quote! { Self })
+        }
+    }
+
+    /// The `#[must_use]` attribute for the builder methods, which is left out when they only
+    /// borrow the builder.
+    fn must_use(&self, global_attr: &GlobalAttr) -> TokenStream {
+        if self.is_mutable(global_attr) {
+            quote! {}
+        } else {
+            global_attr.must_use()
+        }
+    }
+
+    /// Whether the field is stored as is instead of in an `Option`, which is the case for
+    /// `#[build_it(required)]` fields that aren't an `Option`.
+    fn is_plain(&self) -> bool {
+        get_inner_type(&self.field.ty).is_none()
+    }
+
+    /// Whether the field must be set before the value can be built.
+    fn is_required(&self) -> bool {
+        self.attr.default.is_none() && (self.attr.required || self.is_plain())
+    }
+
+    /// Expression for the value of the field when it is not set, if it has a default.
+    fn default_value(&self) -> Option<TokenStream> {
+        match self.attr.default.as_ref()? {
+            FieldDefault::Default => Some(quote! { core::default::Default::default() }),
+            FieldDefault::Fn(path) => Some(quote! { #path() }),
+        }
+    }
+
+    /// Description of the field's default for `describe()`, if it has one.
+    fn default_source(&self) -> Option<String> {
+        match self.attr.default.as_ref()? {
+            FieldDefault::Default => Some("Default::default()".to_string()),
+            FieldDefault::Fn(path) => Some(format!("{}()", type_name(&syn::parse_quote!(#path)))),
+        }
+    }
+
+    /// Wrap a value passed to one of the field's builder methods into the type of the field.
+    fn store(&self, value: TokenStream) -> TokenStream {
+        if self.is_plain() {
+            value
+        } else {
+            quote! { Some(#value) }
+        }
+    }
+
+    /// Doc attributes of the field that are copied onto its builder methods.
+    /// This includes doc comments, unless `#[build_it(no_docs)]` is set or they are replaced with
+    /// `#[build_it(doc = "...")]`, and attributes like `#[doc(alias = "...")]`.
+    fn docs(&self) -> Vec<&syn::Attribute> {
+        let keep_comments = !self.attr.no_docs && self.attr.doc.is_empty();
+        self.attr
+            .doc
+            .iter()
+            .chain(
+                self.field
+                    .attrs
+                    .iter()
+                    .filter(|attr| attr.path().is_ident("doc"))
+                    .filter(move |attr| keep_comments || matches!(attr.meta, syn::Meta::List(_))),
+            )
+            .collect()
+    }
+
+    /// A `#[doc(alias = "...")]` attribute with the name of the field for a renamed setter, so it
+    /// can still be found under that name in rustdoc.
+    fn doc_alias(&self) -> TokenStream {
+        let field_name = self.field.ident.as_ref().unwrap().to_string();
+        match &self.attr.rename {
+            Some(rename) if *rename != field_name => quote! { #[doc(alias = #field_name)] },
+            _ => quote! {},
+        }
+    }
+
+    /// The where clause for the field's setter, with the predicates of
+    /// `#[build_it(bound = "...")]`.
+    fn bounds(&self) -> TokenStream {
+        let bound = &self.attr.bound;
+        if bound.is_empty() {
+            quote! {}
+        } else {
+            quote! { where #(#bound),* }
+        }
+    }
+
+    /// Attributes of the field that are copied onto all of its builder methods, so that e.g. a
+    /// `#[deprecated]` field warns where its setters are called.
+    fn forwarded_attrs(&self) -> Vec<&syn::Attribute> {
+        self.field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("deprecated"))
+            .collect()
+    }
+
+    /// Attributes for all builder methods of the field: the forwarded attributes of the field, and
+    /// `#[doc(hidden)]` if `#[build_it(hidden)]` is set on the field or the struct.
+    fn method_attrs(&self, global_attr: &GlobalAttr) -> TokenStream {
+        let forwarded = self.forwarded_attrs();
+        let hidden = (self.attr.hidden || global_attr.hidden).then(|| quote! { #[doc(hidden)] });
+        quote! {
+            #(#forwarded)*
+            #hidden
+        }
+    }
+}
+
+/// Generate the builder implementation for a struct.
+/// The builder implementation contains a method for each field of the struct, ignoring fields with
+/// a #[build_it(skip)] attribute.
+///
+/// # Example
+///
+/// For a struct with fields `name: Option<String>` and `age: Option<u32>`, the generated
+/// implementation is:
+/// ```
+/// # struct SimpleStruct {
+/// #    name: Option<String>,
+/// #    age: Option<u32>,
+/// # }
+/// impl SimpleStruct {
+///    pub fn name(mut self, name: String) -> Self {
+///        self.name = Some(name);
+///        self
+///     }
+///     pub fn age(mut self, age: u32) -> Self {
+///         self.age = Some(age);
+///         self
+///     }
+/// }
+/// ```
+fn generate_builder_impl(
+    input: &DeriveInput,
+    global_attr: &GlobalAttr,
+    fields: &[ParsedField],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let generics = global_attr.bounded_generics(&input.generics);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let methods = fields
+        .iter()
+        .map(|field| generate_builder_method(name, field, global_attr))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let presets = global_attr
+        .presets
+        .iter()
+        .map(|preset| generate_preset(preset, global_attr, fields));
+    let vis = global_attr.vis();
+    let spawn = if global_attr.template {
+        quote! {
+            /// Returns a copy of this builder, leaving the template untouched.
+            ///
+            /// Use this to configure the shared parts of a value once and customize each instance
+            /// from there.
+            #vis fn spawn(&self) -> Self {
+                core::clone::Clone::clone(self)
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let with = if global_attr.with && global_attr.mutable {
+        quote! {
+            /// Calls `f` with this builder, so conditional configuration can stay in one chain.
+            #vis fn with(&mut self, f: impl FnOnce(&mut Self)) -> &mut Self {
+                f(self);
+                self
+            }
+        }
+    } else if global_attr.with {
+        let must_use = global_attr.must_use();
+        quote! {
+            /// Passes this builder through `f`, so conditional configuration can stay in one
+            /// chain.
+            #must_use
+            #vis fn with(self, f: impl FnOnce(Self) -> Self) -> Self {
+                f(self)
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let counters = if global_attr.counters {
+        generate_counters(global_attr, fields)
+    } else {
+        quote! {}
+    };
+    let set_json = if global_attr.set_json {
+        generate_set_json(global_attr, fields)
+    } else {
+        quote! {}
+    };
+    let to_json_partial = if global_attr.to_json_partial {
+        generate_to_json_partial(global_attr, fields)
+    } else {
+        quote! {}
+    };
+    let reset = if global_attr.reset {
+        generate_reset(global_attr, fields)
+    } else {
+        quote! {}
+    };
+    let merge_from = if global_attr.merge_from {
+        generate_merge_from(global_attr, fields)
+    } else {
+        quote! {}
+    };
+    let into_parts = if global_attr.into_parts {
+        generate_into_parts(global_attr, fields)
+    } else {
+        quote! {}
+    };
+    let describe = if global_attr.describe {
+        generate_describe(input, global_attr, fields)
+    } else {
+        quote! {}
+    };
+    // In builder mode, the handle is generated with the builder struct instead.
+    let shared = if global_attr.shared && !global_attr.builder {
+        generate_shared(input, global_attr, fields, None)
+    } else {
+        quote! {}
+    };
+    let clap = if global_attr.clap {
+        generate_clap_args(input, fields)?
+    } else {
+        quote! {}
+    };
+    // In builder mode, `FromStr` is implemented for the struct with the builder struct instead.
+    let from_str = if global_attr.from_str && !global_attr.builder {
+        generate_from_str(input, global_attr, fields, None)?
+    } else {
+        quote! {}
+    };
+    let tests = if global_attr.gen_tests {
+        generate_tests(input, global_attr, fields)?
+    } else {
+        quote! {}
+    };
+    let deprecations = fields
+        .iter()
+        .filter_map(|field| generate_skip_deprecation(field.field));
+    let allow_case = global_attr.allow_case();
+    Ok(quote! {
+        #allow_case
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#presets)*
+            #spawn
+            #with
+            #counters
+            #reset
+            #merge_from
+            #set_json
+            #to_json_partial
+            #into_parts
+            #(#methods)*
+        }
+        #describe
+        #shared
+        #clap
+        #from_str
+        #tests
+        #(#deprecations)*
+    })
+}
+
+/// Generate the `Shared<Struct>Builder` type, a cloneable handle to a builder behind an
+/// `Arc<Mutex<_>>`. The handle has a builder method for every field, which takes `&self`, and a
+/// `build()` method returning a copy of the current state.
+///
+/// # Example
+///
+/// For a struct `SimpleStruct` with a field `name: Option<String>`, the generated code is:
+/// ```
+/// # #[derive(Clone)]
+/// # struct SimpleStruct {
+/// #    name: Option<String>,
+/// # }
+/// pub struct SharedSimpleStructBuilder(std::sync::Arc<std::sync::Mutex<SimpleStruct>>);
+/// impl SharedSimpleStructBuilder {
+///     pub fn new(builder: SimpleStruct) -> Self {
+///         Self(std::sync::Arc::new(std::sync::Mutex::new(builder)))
+///     }
+///     pub fn name(&self, name: String) -> &Self {
+///         self.0.lock().unwrap_or_else(std::sync::PoisonError::into_inner).name = Some(name);
+///         self
+///     }
+///     pub fn build(&self) -> SimpleStruct {
+///         self.0.lock().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+///     }
+/// }
+/// ```
+///
+/// In `#[build_it(builder)]` mode, `input` is the generated builder and `built` holds the name of
+/// the struct it builds and of its error. The handle is then named after the struct, and `build()`
+/// builds it from a copy of the builder.
+fn generate_shared(
+    input: &DeriveInput,
+    global_attr: &GlobalAttr,
+    fields: &[ParsedField],
+    built: Option<(&syn::Ident, &syn::Ident)>,
+) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let target = built.map_or(name, |(target, _)| target);
+    let shared_name = syn::Ident::new(&format!("Shared{}Builder", target), name.span());
+    let doc = format!(
+        " A cloneable handle to a [`{}`] builder that can be shared between threads and tasks.",
+        target
+    );
+    let lock = quote! {
+        self.0.lock().unwrap_or_else(::std::sync::PoisonError::into_inner)
+    };
+    let build = match built {
+        Some((target, error_name)) => quote! {
+            /// Builds the value from a copy of the builder's current state.
+            pub fn build(&self) -> ::core::result::Result<#target #ty_generics, #error_name> {
+                core::clone::Clone::clone(&*#lock).build()
+            }
+        },
+        None => quote! {
+            /// Returns a snapshot of the builder's current state.
+            pub fn build(&self) -> #name #ty_generics {
+                core::clone::Clone::clone(&*#lock)
+            }
+        },
+    };
+    let setters = buildable_fields(fields).map(|(field, ty)| {
+        let fn_name = field.setter_name();
+        let vis = field.vis();
+        let docs = field.docs();
+        let doc_alias = field.doc_alias();
+        let forwarded = field.method_attrs(global_attr);
+        let bounds = field.bounds();
+        let field_name = field.field.ident.as_ref().unwrap();
+        let param = field.param_name();
+        let (arg_ty, value) = field.setter_argument(global_attr, ty);
+        let record = generate_metrics(field, global_attr);
+        let null_setter = field.attr.nullable.then(|| {
+            let null_fn_name = global_attr.method_name(format!("null_{}", fn_name));
+            let doc = format!(
+                " Sets `{}` to `Some(None)`, marking it as explicitly empty rather than unset.",
+                field_name
+            );
+            quote! {
+                #[doc = #doc]
+                #forwarded
+                #vis fn #null_fn_name(&self) -> &Self {
+                    #record
+                    #lock.#field_name = Some(None);
+                    self
+                }
+            }
+        });
+        quote! {
+            #(#docs)*
+            #doc_alias
+            #forwarded
+            #vis fn #fn_name(&self, #param: #arg_ty) -> &Self
+            #bounds
+            {
+                #record
+                #lock.#field_name = #value;
+                self
+            }
+
+            #null_setter
+        }
+    });
+    quote! {
+        #[doc = #doc]
+        #vis struct #shared_name #generics (
+            ::std::sync::Arc<::std::sync::Mutex<#name #ty_generics>>
+        ) #where_clause;
+
+        impl #impl_generics core::clone::Clone for #shared_name #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                Self(::std::sync::Arc::clone(&self.0))
+            }
+        }
+
+        impl #impl_generics core::convert::From<#name #ty_generics> for #shared_name #ty_generics
+        #where_clause
+        {
+            fn from(builder: #name #ty_generics) -> Self {
+                Self::new(builder)
+            }
+        }
+
+        impl #impl_generics #shared_name #ty_generics #where_clause {
+            /// Creates a new shared handle to the given builder.
+            pub fn new(builder: #name #ty_generics) -> Self {
+                Self(::std::sync::Arc::new(::std::sync::Mutex::new(builder)))
+            }
+
+            #(#setters)*
+
+            #build
+        }
+    }
+}
+
+/// Generate the `<Struct>Args` type deriving `clap::Args`, with an optional `--flag` for every
+/// buildable field, and the conversion of the parsed arguments into the builder.
+///
+/// # Example
+///
+/// For a struct `SimpleStruct` with a documented field `name: Option<String>`, the generated
+/// code is:
+/// ```ignore
+/// #[derive(::clap::Args, Debug, Clone, Default)]
+/// pub struct SimpleStructArgs {
+///     /// Name of the person
+///     #[arg(long)]
+///     pub name: Option<String>,
+/// }
+/// impl SimpleStructArgs {
+///     pub fn apply(self, mut builder: SimpleStruct) -> SimpleStruct {
+///         if let Some(name) = self.name {
+///             builder.name = Some(name);
+///         }
+///         builder
+///     }
+/// }
+/// impl From<SimpleStructArgs> for SimpleStruct {
+///     fn from(args: SimpleStructArgs) -> Self {
+///         args.apply(Default::default())
+///     }
+/// }
+/// ```
+fn generate_clap_args(input: &DeriveInput, fields: &[ParsedField]) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "`clap` does not support generic structs",
+        ));
+    }
+    let args_name = syn::Ident::new(&format!("{}Args", name), name.span());
+    let doc = format!(" Command-line arguments for configuring a [`{}`].", name);
+    let args = buildable_fields(fields).map(|(field, ty)| {
+        let field_name = field.field.ident.as_ref().unwrap();
+        let docs = field.docs();
+        quote! {
+            #(#docs)*
+            #[arg(long)]
+            pub #field_name: Option<#ty>,
+        }
+    });
+    let apply = buildable_fields(fields).map(|(field, _)| {
+        let field_name = field.field.ident.as_ref().unwrap();
+        let value = field.store(quote! { #field_name });
+        quote! {
+            if let Some(#field_name) = self.#field_name {
+                builder.#field_name = #value;
+            }
+        }
+    });
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(::clap::Args, Debug, Clone, Default)]
+        #vis struct #args_name {
+            #(#args)*
+        }
+
+        impl #args_name {
+            /// Sets every field of the builder that was passed on the command line.
+            pub fn apply(self, mut builder: #name) -> #name {
+                #(#apply)*
+                builder
+            }
+        }
+
+        impl core::convert::From<#args_name> for #name {
+            fn from(args: #args_name) -> Self {
+                args.apply(core::default::Default::default())
+            }
+        }
+    })
+}
+
+/// Generate a `FromStr` implementation parsing a `key=value,key=value` list, where each key is
+/// the name of a builder method, like the names of `generate_set_json`, and each value is parsed
+/// with the `FromStr` of the method's argument type and passed to the method.
+///
+/// In `#[build_it(builder)]` mode, `built` is the struct that `FromStr` is implemented for, by
+/// building it from the builder.
+///
+/// # Example
+///
+/// For a struct `Storage` with a field `size: Option<u64>`, the generated code is:
+/// ```ignore
+/// impl core::str::FromStr for Storage {
+///     type Err = String;
+///     fn from_str(s: &str) -> Result<Self, Self::Err> {
+///         let mut builder = <Storage as Default>::default();
+///         for pair in s.split(',').filter(|pair| !pair.trim().is_empty()) {
+///             let (key, value) = pair.split_once('=').ok_or_else(|| ...)?;
+///             match key.trim() {
+///                 "size" => {
+///                     let value = <u64 as core::str::FromStr>::from_str(value.trim())
+///                         .map_err(|err| format!("invalid value for `size`: {}", err))?;
+///                     builder = builder.size(value);
+///                 }
+///                 key => return Err(format!("unknown key `{}`", key)),
+///             }
+///         }
+///         Ok(builder)
+///     }
+/// }
+/// ```
+fn generate_from_str(
+    input: &DeriveInput,
+    global_attr: &GlobalAttr,
+    fields: &[ParsedField],
+    built: Option<&syn::Ident>,
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let generics = global_attr.bounded_generics(&input.generics);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let target = built.unwrap_or(name);
+    let boxed =
+        buildable_fields(fields).find(|(field, ty)| field.concrete_argument_type(ty).is_none());
+    if let Some((field, _)) = boxed {
+        return Err(syn::Error::new(
+            field.field.span(),
+            "`from_str` can't parse the values of boxed fields, use `#[build_it(skip)]`",
+        ));
+    }
+    let arms = buildable_fields(fields).map(|(field, ty)| {
+        let key = field.setter_name().to_string();
+        let message = format!("invalid value for `{}`: {{}}", key);
+        let value_ty = field
+            .concrete_argument_type(ty)
+            .expect("boxed fields are checked above");
+        let set = field.setter_call(global_attr, quote! { value });
+        quote! {
+            #key => {
+                let value = <#value_ty as core::str::FromStr>::from_str(value.trim())
+                    .map_err(|err| ::std::format!(#message, err))?;
+                #set
+            }
+        }
+    });
+    let output = if built.is_some() {
+        quote! { builder.build().map_err(|err| ::std::string::ToString::to_string(&err)) }
+    } else {
+        quote! { ::core::result::Result::Ok(builder) }
+    };
+    Ok(quote! {
+        impl #impl_generics core::str::FromStr for #target #ty_generics #where_clause {
+            type Err = ::std::string::String;
+
+            fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                let mut builder = <#name #ty_generics as core::default::Default>::default();
+                for pair in s.split(',').filter(|pair| !pair.trim().is_empty()) {
+                    // `value` is unused if there are no buildable fields.
+                    #[allow(unused_variables)]
+                    let (key, value) = pair.split_once('=').ok_or_else(|| {
+                        ::std::format!("expected `key=value`, found `{}`", pair.trim())
+                    })?;
+                    match key.trim() {
+                        #(#arms)*
+                        key => return ::core::result::Result::Err(
+                            ::std::format!("unknown key `{}`", key)
+                        ),
+                    }
+                }
+                #output
+            }
+        }
+    })
+}
+
+/// Generate a `#[cfg(test)]` module with a test for every builder method.
+/// Each test calls the method with the default value of its argument and checks that the field
+/// holds the converted value.
+///
+/// # Example
+///
+/// For a struct `SimpleStruct` with a field `name: Option<String>`, the generated module is:
+/// ```ignore
+/// #[cfg(test)]
+/// mod simplestruct_builder_tests {
+///     use super::*;
+///
+///     #[test]
+///     fn name() {
+///         let value: String = Default::default();
+///         let mut builder = <SimpleStruct as Default>::default();
+///         builder = builder.name(Clone::clone(&value));
+///         assert_eq!(builder.name, Some(value));
+///     }
+/// }
+/// ```
+fn generate_tests(
+    input: &DeriveInput,
+    global_attr: &GlobalAttr,
+    fields: &[ParsedField],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "`gen_tests` does not support generic structs",
+        ));
+    }
+    let mod_name = syn::Ident::new(
+        &format!("{}_builder_tests", name.to_string().to_lowercase()),
+        name.span(),
+    );
+    let boxed =
+        buildable_fields(fields).find(|(field, ty)| field.attr.boxed || is_boxed_callback(ty));
+    if let Some((field, _)) = boxed {
+        return Err(syn::Error::new(
+            field.field.span(),
+            "`gen_tests` can't compare the values of boxed fields, use `#[build_it(skip)]` or set them in a test of your own",
+        ));
+    }
+    let tests = buildable_fields(fields).map(|(field, ty)| {
+        let ParsedField { attr, .. } = field;
+        let field_name = field.field.ident.as_ref().unwrap();
+        let fn_name = field.setter_name();
+        let value_ty = field
+            .concrete_argument_type(ty)
+            .expect("boxed fields are checked above");
+        // The value the setter stores for the default value.
+        let expected = if let Some(with) = &attr.with {
+            quote! { #with(value) }
+        } else if let Some(map) = &attr.map {
+            quote! { (#map)(value) }
+        } else if let Some(wrapper) = attr.shared_pointer() {
+            match wrapper {
+                "Arc" => quote! { ::std::sync::Arc::new(value) },
+                _ => quote! { ::std::rc::Rc::new(value) },
+            }
+        } else {
+            quote! { value }
+        };
+        let expected = if attr.nullable {
+            field.store(quote! { Some(#expected) })
+        } else {
+            field.store(expected)
+        };
+        let set = field.setter_call(global_attr, quote! { core::clone::Clone::clone(&value) });
+        quote! {
+            #[test]
+            fn #fn_name() {
+                let value: #value_ty = core::default::Default::default();
+                let mut builder = <#name as core::default::Default>::default();
+                #set
+                assert_eq!(builder.#field_name, #expected);
+            }
+        }
+    });
+    Ok(quote! {
+        #[cfg(test)]
+        mod #mod_name {
+            use super::*;
+
+            #(#tests)*
+        }
+    })
+}
+
+/// Generate a deprecation warning for a field using the deprecated `#[skip]` attribute.
+///
+/// Proc-macros can't emit warnings directly, so this references a `#[deprecated]` constant with the
+/// span of the attribute, which makes the compiler report the warning at the `#[skip]` itself.
+fn generate_skip_deprecation(field: &syn::Field) -> Option<TokenStream> {
+    let attr = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("skip"))?;
+    let usage = quote_spanned! {attr.path().span()=> skip};
+    Some(quote! {
+        const _: () = {
+            #[deprecated(note = "`#[skip]` is deprecated, use `#[build_it(skip)]` instead")]
+            #[allow(non_upper_case_globals)]
+            const skip: () = ();
+            #usage
+        };
+    })
+}
+
+/// Generate the `set_count()` and `is_empty()` methods, which report how many buildable fields
+/// are currently set.
+fn generate_counters(global_attr: &GlobalAttr, fields: &[ParsedField]) -> TokenStream {
+    let vis = global_attr.vis();
+    let set = buildable_fields(fields)
+        .filter(|(field, _)| !field.is_plain())
+        .map(|(field, _)| {
+            let field_name = field.field.ident.as_ref().unwrap();
+            quote! { self.#field_name.is_some() }
+        });
+    quote! {
+        /// Returns the number of fields that have been set on this builder.
+        #vis fn set_count(&self) -> usize {
+            0 #(+ usize::from(#set))*
+        }
+        /// Returns `true` if no field has been set on this builder.
+        #vis fn is_empty(&self) -> bool {
+            self.set_count() == 0
+        }
+    }
+}
+
+/// Generate the `reset()` method, which unsets every buildable `Option` field so the builder can
+/// be reused.
+fn generate_reset(global_attr: &GlobalAttr, fields: &[ParsedField]) -> TokenStream {
+    let vis = global_attr.vis();
+    let names = buildable_fields(fields)
+        .filter(|(field, _)| !field.is_plain())
+        .map(|(field, _)| field.field.ident.as_ref().unwrap());
+    quote! {
+        /// Unsets every field of this builder, leaving skipped fields and fields that aren't an
+        /// `Option` untouched.
+        #vis fn reset(&mut self) {
+            #(self.#names = None;)*
+        }
+    }
+}
+
+/// Generate the `merge_from()` method, which copies every buildable `Option` field that is set in
+/// another builder.
+fn generate_merge_from(global_attr: &GlobalAttr, fields: &[ParsedField]) -> TokenStream {
+    let vis = global_attr.vis();
+    let must_use = global_attr.must_use();
+    let names = buildable_fields(fields)
+        .filter(|(field, _)| !field.is_plain())
+        .map(|(field, _)| field.field.ident.as_ref().unwrap());
+    quote! {
+        /// Copies every field that is set in `other` into this builder, keeping the current value
+        /// of the fields that aren't.
+        ///
+        /// Chain calls to layer configuration sources, with later sources taking precedence.
+        #must_use
+        #vis fn merge_from(mut self, other: Self) -> Self {
+            #(
+                if other.#names.is_some() {
+                    self.#names = other.#names;
+                }
+            )*
+            self
+        }
+    }
+}
+
+/// Generate the `into_parts()` method, which returns all fields as a tuple in declaration order.
+fn generate_into_parts(global_attr: &GlobalAttr, fields: &[ParsedField]) -> TokenStream {
+    let vis = global_attr.vis();
+    let names = fields
+        .iter()
+        .map(|field| field.field.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+    let types = fields.iter().map(|field| &field.field.ty);
+    quote! {
+        /// Decomposes the builder into its fields, in declaration order.
+        #vis fn into_parts(self) -> (#(#types,)*) {
+            // `..` skips the marker of the builder in `#[build_it(builder)]` mode.
+            let Self { #(#names,)* .. } = self;
+            (#(#names,)*)
+        }
+    }
+}
+
+/// Generate the `set_json()` method, which deserializes a `serde_json::Value` into the field whose
+/// builder method has the given name, like the keys of `generate_from_str`.
+///
+/// # Example
+///
+/// For a field `age: Option<u32>`, the generated method is:
+/// ```ignore
+/// pub fn set_json(mut self, name: &str, value: ::serde_json::Value) -> Result<Self, ::serde_json::Error> {
+///     use ::serde::de::Error as _;
+///     match name {
+///         "age" => self.age = Some(::serde_json::from_value(value).map_err(|err| {
+///             ::serde_json::Error::custom(format_args!("invalid value for `age`: {}", err))
+///         })?),
+///         _ => return Err(::serde_json::Error::custom(format_args!("unknown field `{}`", name))),
+///     }
+///     Ok(self)
+/// }
+/// ```
+fn generate_set_json(global_attr: &GlobalAttr, fields: &[ParsedField]) -> TokenStream {
+    let vis = global_attr.vis();
+    let arms = buildable_fields(fields).map(|(field, _)| {
+        let field_name = field.field.ident.as_ref().unwrap();
+        let key = field.setter_name().to_string();
+        let message = format!("invalid value for `{}`: {{}}", key);
+        let value = field.store(quote! {
+            ::serde_json::from_value(value).map_err(|err| {
+                ::serde_json::Error::custom(format_args!(#message, err))
+            })?
+        });
+        quote! {
+            #key => {
+                self.#field_name = #value;
+            }
+        }
+    });
+    quote! {
+        /// Deserializes `value` into the field set by the builder method called `name` and sets
+        /// it.
+        ///
+        /// Fails if there is no such field, or if `value` doesn't match the field's type.
+        #vis fn set_json(
+            mut self,
+            name: &str,
+            value: ::serde_json::Value,
+        ) -> ::core::result::Result<Self, ::serde_json::Error> {
+            use ::serde::de::Error as _;
+            match name {
+                #(#arms)*
+                _ => {
+                    return Err(::serde_json::Error::custom(format_args!(
+                        "unknown field `{}`",
+                        name
+                    )))
+                }
+            }
+            Ok(self)
+        }
+    }
+}
+
+/// Generate the `to_json_partial()` method, which serializes the fields that are set into a JSON
+/// object. Fields are keyed by their `#[serde(rename = "...")]` name, or their field name.
+/// Fields with `#[build_it(sensitive)]` are left out.
+///
+/// # Example
+///
+/// For a field `age: Option<u32>`, the generated method is:
+/// ```ignore
+/// pub fn to_json_partial(&self) -> ::serde_json::Value {
+///     let mut map = ::serde_json::Map::new();
+///     if let Some(value) = &self.age {
+///         map.insert("age".to_string(), ::serde_json::to_value(value).expect("..."));
+///     }
+///     ::serde_json::Value::Object(map)
+/// }
+/// ```
+fn generate_to_json_partial(global_attr: &GlobalAttr, fields: &[ParsedField]) -> TokenStream {
+    let vis = global_attr.vis();
+    let entries = buildable_fields(fields)
+        .filter(|(field, _)| !field.attr.sensitive)
+        .map(|(field, _)| {
+            let field_name = field.field.ident.as_ref().unwrap();
+            let key = serde_rename(field.field).unwrap_or_else(|| field_name.to_string());
+            let message = format!("failed to serialize `{}`", field_name);
+            let value = if field.is_plain() {
+                quote! { Some(&self.#field_name) }
+            } else {
+                quote! { &self.#field_name }
+            };
+            quote! {
+                if let Some(value) = #value {
+                    map.insert(
+                        #key.to_string(),
+                        ::serde_json::to_value(value).expect(#message),
+                    );
+                }
+            }
+        });
+    quote! {
+        /// Serializes the fields that are set into a JSON object, leaving out unset and
+        /// sensitive fields.
+        ///
+        /// # Panics
+        ///
+        /// Panics if one of the fields fails to serialize, e.g. a map with non-string keys.
+        #vis fn to_json_partial(&self) -> ::serde_json::Value {
+            let mut map = ::serde_json::Map::new();
+            #(#entries)*
+            ::serde_json::Value::Object(map)
+        }
+    }
+}
+
+/// Get the serialized name of a field from a `#[serde(rename = "...")]` or
+/// `#[serde(rename(serialize = "..."))]` attribute.
+fn serde_rename(field: &syn::Field) -> Option<String> {
+    let mut rename = None;
+    let attrs = field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"));
+    for attr in attrs {
+        // Other serde options are none of our business, so parse errors are ignored.
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(syn::Token![=]) {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                rename = Some(lit.value());
+            } else if meta.path.is_ident("rename") {
+                meta.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("serialize") {
+                        let lit: syn::LitStr = meta.value()?.parse()?;
+                        rename = Some(lit.value());
+                    } else {
+                        let _: syn::LitStr = meta.value()?.parse()?;
+                    }
+                    Ok(())
+                })?;
+            } else if meta.input.peek(syn::Token![=]) {
+                let _: syn::Expr = meta.value()?.parse()?;
+            }
+            Ok(())
+        });
+    }
+    rename
+}
+
+/// Generate the `describe()` method and the `<Struct>FieldInfo` type it returns.
+/// Each buildable field is described by its name, type, the first paragraph of its
+/// documentation, and whether it is required.
+///
+/// # Example
+///
+/// For a struct `SimpleStruct` with a documented field `name: Option<String>`, the generated
+/// code is:
+/// ```
+/// # struct SimpleStruct {
+/// #    name: Option<String>,
+/// # }
+/// /// Runtime description of a `SimpleStruct` builder field.
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// pub struct SimpleStructFieldInfo {
+///     pub name: &'static str,
+///     pub ty: &'static str,
+///     pub doc: &'static str,
+///     pub required: bool,
+///     pub default: Option<&'static str>,
+/// }
+/// impl SimpleStruct {
+///     pub fn describe() -> &'static [SimpleStructFieldInfo] {
+///         &[SimpleStructFieldInfo {
+///             name: "name",
+///             ty: "String",
+///             doc: "Name of the person",
+///             required: false,
+///             default: None,
+///         }]
+///     }
+/// }
+/// ```
+fn generate_describe(
+    input: &DeriveInput,
+    global_attr: &GlobalAttr,
+    fields: &[ParsedField],
+) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let fn_vis = global_attr.vis();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let info_name = syn::Ident::new(&format!("{}FieldInfo", name), name.span());
+    let struct_doc = format!(" Runtime description of a `{}` builder field.", name);
+    let describe_doc = format!(
+        " Returns a description of every field that can be set through the `{}` builder.",
+        name
+    );
+    let infos = buildable_fields(fields).map(|(field, ty)| {
+        let field_name = field.field.ident.as_ref().unwrap().to_string();
+        let ty = type_name(ty);
+        let doc = if field.attr.no_docs {
+            String::new()
+        } else {
+            doc_summary(&field.field.attrs)
+        };
+        let required = field.is_required();
+        let default = match field.default_source() {
+            Some(source) => quote! { Some(#source) },
+            None => quote! { None },
+        };
+        quote! {
+            #info_name {
+                name: #field_name,
+                ty: #ty,
+                doc: #doc,
+                required: #required,
+                default: #default,
+            }
+        }
+    });
+    quote! {
+        #[doc = #struct_doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #vis struct #info_name {
+            /// Name of the field.
+            pub name: &'static str,
+            /// Type accepted by the field's builder method.
+            pub ty: &'static str,
+            /// First paragraph of the field's documentation.
+            pub doc: &'static str,
+            /// Whether the field must be set before the value is complete.
+            pub required: bool,
+            /// Source of the value used when the field is not set, if any.
+            pub default: Option<&'static str>,
+        }
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc = #describe_doc]
+            #fn_vis fn describe() -> &'static [#info_name] {
+                &[#(#infos),*]
+            }
+        }
+    }
+}
+
+/// Render a type the way it is usually written, e.g. `Vec<u8>` instead of `Vec < u8 >`.
+fn type_name(ty: &syn::Type) -> String {
+    quote!(#ty)
+        .to_string()
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace(" <", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace("& ", "&")
+        .replace("' ", "'")
+}
+
+/// Turn a type name into something that can be part of an identifier, e.g. `&str` into `str`.
+fn ident_fragment(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_lowercase()
+}
+
+/// Get the first paragraph of the doc comments in `attrs`, joined into a single line.
+fn doc_summary(attrs: &[syn::Attribute]) -> String {
+    let lines = attrs.iter().filter_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(lit),
+                ..
+            }) => Some(lit.value()),
+            _ => None,
+        },
+        _ => None,
+    });
+    let mut summary = Vec::new();
+    for line in lines {
+        let line = line.trim().to_string();
+        if line.is_empty() {
+            if summary.is_empty() {
+                continue;
+            }
+            break;
+        }
+        summary.push(line);
+    }
+    summary.join(" ")
+}
+
+/// Generate a named preset constructor.
+/// The constructor starts from `Default::default()` and sets every field listed in the preset.
+///
+/// # Example
+///
+/// For `#[build_it(preset(name = "production", workers = 16))]`, the generated method is:
+/// ```
+/// # #[derive(Default)]
+/// # struct SimpleStruct {
+/// #    workers: Option<u32>,
+/// # }
+/// # impl SimpleStruct {
+/// pub fn production() -> Self {
+///     Self {
+///         workers: Some(16),
+///         ..Default::default()
+///     }
+/// }
+/// # }
+/// ```
+fn generate_preset(
+    preset: &Preset,
+    global_attr: &GlobalAttr,
+    fields: &[ParsedField],
+) -> TokenStream {
+    let fn_name = &preset.name;
+    let vis = global_attr.vis();
+    let doc = format!(
+        " Returns a builder pre-populated with the `{}` preset.",
+        preset.name
+    );
+    let values = preset.values.iter().map(|(field_name, value)| {
+        let field = fields
+            .iter()
+            .find(|field| field.field.ident.as_ref() == Some(field_name));
+        let Some(field) = field else {
+            // Let the compiler report the unknown field at the preset key.
+            return quote! { #field_name: #value };
+        };
+        let value = if field.is_into(global_attr) {
+            quote! { core::convert::Into::into(#value) }
+        } else {
+            quote! { #value }
+        };
+        if get_inner_type(&field.field.ty).is_some() && !is_skipped(field) {
+            quote! { #field_name: Some(#value) }
+        } else {
+            quote! { #field_name: #value }
+        }
+    });
+    quote! {
+        #[doc = #doc]
+        #vis fn #fn_name() -> Self {
+            Self {
+                #(#values,)*
+                ..core::default::Default::default()
+            }
+        }
+    }
+}
+
+/// Generate the builder method for a field.
+/// The method has the same name as the field and takes the field type by value.
+///
+/// # Example
+///
+/// For a field `name: Option<String>`, the generated method is:
+/// ```
+/// # struct SimpleStruct {
+/// #    name: Option<String>,
+/// # }
+/// # impl SimpleStruct {
+/// pub fn name(mut self, name: String) -> Self {
+///    self.name = Some(name);
+///    self
+/// }
+/// # }
+/// ```
+fn generate_builder_method(
+    name: &syn::Ident,
+    field: &ParsedField,
+    global_attr: &GlobalAttr,
+) -> syn::Result<TokenStream> {
+    if is_skipped(field) {
+        return Ok(generate_test_setter(field, global_attr));
+    }
+
+    let fn_name = field.setter_name();
+    let vis = field.vis();
+    let (receiver, output) = field.receiver(global_attr);
+    let must_use = field.must_use(global_attr);
+    let inline = global_attr.inline();
+    let docs = field.docs();
+    let doc_alias = field.doc_alias();
+    let forwarded = field.method_attrs(global_attr);
+    let record = generate_metrics(field, global_attr);
+    let parsed = field;
+    let ParsedField { field, attr } = field;
+
+    let field_name = field.ident.as_ref().unwrap();
+    let field_ty = match get_inner_type(&field.ty) {
+        Some(field_ty) => field_ty,
+        None if attr.required || attr.default.is_some() => &field.ty,
+        None => {
+            return Err(syn::Error::new(
+                field.span(),
+                "Builder only works on Option<T> fields. Consider using #[build_it(required)] for fields that must be set, or #[skip] to skip fields that should not be optional.",
+            ));
+        }
+    };
+
+    let param = parsed.param_name();
+    let (arg_ty, value) = parsed.setter_argument(global_attr, field_ty);
+    let example = generate_doc_example(name, parsed, global_attr, !docs.is_empty());
+    let bounds = parsed.bounds();
+    let setter = quote! {
+        #(#docs)*
+        #doc_alias
+        #example
+        #must_use
+        #forwarded
+        #inline
+        #vis fn #fn_name(#receiver, #param: #arg_ty) -> #output
+        #bounds
+        {
+            #record
+            self.#field_name = #value;
+            self
+        }
+    };
+    // Aliases forward to the setter, so they only need to borrow or move the builder.
+    let alias_receiver = if parsed.is_mutable(global_attr) {
+        quote! { &mut self }
+    } else {
+        quote! { self }
+    };
+    let alias_setters = attr.alias.iter().map(|alias| {
+        let doc = format!(" Alias of [`Self::{}`].", fn_name);
+        quote! {
+            #[doc = #doc]
+            #must_use
+            #forwarded
+            #inline
+            #vis fn #alias(#alias_receiver, #param: #arg_ty) -> #output
+            #bounds
+            {
+                self.#fn_name(#param)
+            }
+        }
+    });
+    let dual_setter = if attr.dual || global_attr.dual {
+        let set_fn_name = global_attr.method_name(format!("set_{}", fn_name));
+        quote! {
+            #(#docs)*
+            #forwarded
+            #inline
+            #vis fn #set_fn_name(&mut self, #param: #arg_ty) -> &mut Self
+            #bounds
+            {
+                #record
+                self.#field_name = #value;
+                self
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let try_from_setters = attr.try_from.iter().map(|source_ty| {
+        let suffix = ident_fragment(&type_name(source_ty));
+        let try_fn_name = global_attr.method_name(format!("{}_from_{}", fn_name, suffix));
+        let doc = format!(
+            " Sets `{}` from a `{}`, failing if the value can't be converted.",
+            field_name,
+            type_name(source_ty)
+        );
+        let value = parsed.store(quote! { core::convert::TryFrom::try_from(#param)? });
+        quote! {
+            #[doc = #doc]
+            #forwarded
+            #inline
+            #vis fn #try_fn_name(
+                #receiver,
+                #param: #source_ty,
+            ) -> ::core::result::Result<#output, <#field_ty as core::convert::TryFrom<#source_ty>>::Error> {
+                #record
+                self.#field_name = #value;
+                Ok(self)
+            }
+        }
+    });
+    let try_into_setter = attr.try_into.then(|| {
+        let try_fn_name = global_attr.method_name(format!("try_{}", fn_name));
+        let doc = format!(
+            " Sets `{}` from any value that can be converted, failing if the conversion fails.",
+            field_name
+        );
+        let value = parsed.store(quote! { core::convert::TryInto::try_into(#param)? });
+        quote! {
+            #[doc = #doc]
+            #forwarded
+            #inline
+            #vis fn #try_fn_name<V>(#receiver, #param: V) -> ::core::result::Result<#output, V::Error>
+            where
+                V: core::convert::TryInto<#field_ty>,
+            {
+                #record
+                self.#field_name = #value;
+                Ok(self)
+            }
+        }
+    });
+    let bitflags_setter = attr.bitflags.as_ref().map(|insert_fn_name| {
+        let doc = format!(" Adds the given flags to `{}`.", field_name);
+        let flags = if parsed.is_plain() {
+            quote! { self.#field_name }
+        } else {
+            quote! { self.#field_name.unwrap_or_else(<#field_ty>::empty) }
+        };
+        let value = parsed.store(quote! { flags });
+        quote! {
+            #[doc = #doc]
+            #must_use
+            #forwarded
+            #inline
+            #vis fn #insert_fn_name(#receiver, #field_name: #field_ty) -> #output {
+                #record
+                let mut flags = #flags;
+                flags.insert(#field_name);
+                self.#field_name = #value;
+                self
+            }
+        }
+    });
+    // The collection of `add`, `each`, `insert` and `extend` methods, created on first use.
+    let collection = if parsed.is_plain() {
+        quote! { &mut self.#field_name }
+    } else {
+        quote! { self.#field_name.get_or_insert_with(core::default::Default::default) }
+    };
+    let add_setter = attr.add.as_ref().map(|add_fn_name| {
+        let Some(element_ty) = get_element_type(field_ty) else {
+            return Err(syn::Error::new(
+                field.ty.span(),
+                "`add` only works on collections of builders, e.g. Option<Vec<T>>",
+            ));
+        };
+        let doc = format!(
+            " Configures a new element with the given closure and adds it to `{}`.",
+            field_name
+        );
+        Ok(quote! {
+            #[doc = #doc]
+            #must_use
+            #forwarded
+            #inline
+            #vis fn #add_fn_name(#receiver, build: impl FnOnce(#element_ty) -> #element_ty) -> #output {
+                #record
+                let element = build(<#element_ty as core::default::Default>::default());
+                core::iter::Extend::extend(#collection, core::iter::once(element));
+                self
+            }
+        })
+    })
+    .transpose()?;
+    let each_setter = attr
+        .each
+        .as_ref()
+        .map(|each_fn_name| {
+            let Some(element_ty) = get_element_type(field_ty) else {
+                return Err(syn::Error::new(
+                    field.ty.span(),
+                    "`each` only works on collections, e.g. Option<Vec<T>>",
+                ));
+            };
+            let doc = format!(" Adds a single element to `{}`.", field_name);
+            let (arg_ty, element) = if parsed.is_into(global_attr) {
+                (
+                    quote! { impl core::convert::Into<#element_ty> },
+                    quote! { item.into() },
+                )
+            } else {
+                (quote! { #element_ty }, quote! { item })
+            };
+            Ok(quote! {
+                #[doc = #doc]
+                #must_use
+                #forwarded
+                #inline
+                #vis fn #each_fn_name(#receiver, item: #arg_ty) -> #output {
+                    #record
+                    core::iter::Extend::extend(#collection, core::iter::once(#element));
+                    self
+                }
+            })
+        })
+        .transpose()?;
+    let insert_setter = attr
+        .insert
+        .as_ref()
+        .map(|insert_fn_name| {
+            let insert_fn_name = match insert_fn_name {
+                Some(insert_fn_name) => insert_fn_name.clone(),
+                None => global_attr.method_name(format!("insert_{}", fn_name)),
+            };
+            let Some((key_ty, value_ty)) = get_map_types(field_ty) else {
+                return Err(syn::Error::new(
+                    field.ty.span(),
+                    "`insert` only works on maps, e.g. Option<HashMap<K, V>>",
+                ));
+            };
+            let doc = format!(" Inserts a single entry into `{}`.", field_name);
+            let (key_arg, value_arg, entry) = if parsed.is_into(global_attr) {
+                (
+                    quote! { impl core::convert::Into<#key_ty> },
+                    quote! { impl core::convert::Into<#value_ty> },
+                    quote! { (key.into(), value.into()) },
+                )
+            } else {
+                (
+                    quote! { #key_ty },
+                    quote! { #value_ty },
+                    quote! { (key, value) },
+                )
+            };
+            Ok(quote! {
+                #[doc = #doc]
+                #must_use
+                #forwarded
+                #inline
+                #vis fn #insert_fn_name(#receiver, key: #key_arg, value: #value_arg) -> #output {
+                    #record
+                    core::iter::Extend::extend(#collection, core::iter::once(#entry));
+                    self
+                }
+            })
+        })
+        .transpose()?;
+    let extend_setter = attr.extend.then(|| {
+        let extend_fn_name = global_attr.method_name(format!("{}_extend", fn_name));
+        let doc = format!(
+            " Adds every element of `items` to `{}`, e.g. from an iterator or a slice.",
+            field_name
+        );
+        quote! {
+            #[doc = #doc]
+            #must_use
+            #forwarded
+            #inline
+            #vis fn #extend_fn_name<I>(#receiver, items: I) -> #output
+            where
+                I: core::iter::IntoIterator,
+                #field_ty: core::iter::Extend<I::Item>,
+            {
+                #record
+                core::iter::Extend::extend(#collection, items);
+                self
+            }
+        }
+    });
+    let base64_setter = attr.base64.then(|| {
+        let decode_fn_name = global_attr.method_name(format!("{}_base64", fn_name));
+        let doc = format!(" Sets `{}` from a base64-encoded string.", field_name);
+        let value = parsed.store(quote! {
+            ::base64::engine::general_purpose::STANDARD.decode(#param)?.into()
+        });
+        quote! {
+            #[doc = #doc]
+            #forwarded
+            #inline
+            #vis fn #decode_fn_name(#receiver, #param: &str) -> ::core::result::Result<#output, ::base64::DecodeError> {
+                use ::base64::Engine as _;
+                #record
+                self.#field_name = #value;
+                Ok(self)
+            }
+        }
+    });
+    let hex_setter = attr.hex.then(|| {
+        let decode_fn_name = global_attr.method_name(format!("{}_hex", fn_name));
+        let doc = format!(" Sets `{}` from a hex-encoded string.", field_name);
+        let value = parsed.store(quote! { ::hex::decode(#param)?.into() });
+        quote! {
+            #[doc = #doc]
+            #forwarded
+            #inline
+            #vis fn #decode_fn_name(#receiver, #param: &str) -> ::core::result::Result<#output, ::hex::FromHexError> {
+                #record
+                self.#field_name = #value;
+                Ok(self)
+            }
+        }
+    });
+    let humantime_setter = attr.humantime.then(|| {
+        let parse_fn_name = global_attr.method_name(format!("{}_str", fn_name));
+        let doc = format!(
+            " Sets `{}` from a human-friendly duration string, e.g. `{}(\"30s\")`.",
+            field_name, parse_fn_name
+        );
+        let value = parsed.store(quote! { ::humantime::parse_duration(#param)? });
+        quote! {
+            #[doc = #doc]
+            #forwarded
+            #inline
+            #vis fn #parse_fn_name(#receiver, #param: &str) -> ::core::result::Result<#output, ::humantime::DurationError> {
+                #record
+                self.#field_name = #value;
+                Ok(self)
+            }
+        }
+    });
+    let duration_setters = attr.duration.then(|| {
+        let secs_fn_name = global_attr.method_name(format!("{}_secs", fn_name));
+        let millis_fn_name = global_attr.method_name(format!("{}_millis", fn_name));
+        let secs_doc = format!(" Sets `{}` to the given number of seconds.", field_name);
+        let millis_doc = format!(
+            " Sets `{}` to the given number of milliseconds.",
+            field_name
+        );
+        let secs = parsed.store(quote! { core::time::Duration::from_secs(secs) });
+        let millis = parsed.store(quote! { core::time::Duration::from_millis(millis) });
+        quote! {
+            #[doc = #secs_doc]
+            #must_use
+            #forwarded
+            #inline
+            #vis fn #secs_fn_name(#receiver, secs: u64) -> #output {
+                #record
+                self.#field_name = #secs;
+                self
+            }
+            #[doc = #millis_doc]
+            #must_use
+            #forwarded
+            #inline
+            #vis fn #millis_fn_name(#receiver, millis: u64) -> #output {
+                #record
+                self.#field_name = #millis;
+                self
+            }
+        }
+    });
+    let fmt_setter = attr.fmt.then(|| {
+        let fmt_fn_name = global_attr.method_name(format!("{}_fmt", fn_name));
+        let doc = format!(
+            " Sets `{}` from format arguments, e.g. `{}(format_args!(\"job {{id}}\"))`.",
+            field_name, fmt_fn_name
+        );
+        let value = parsed.store(quote! { ::std::fmt::format(#param).into() });
+        quote! {
+            #[doc = #doc]
+            #must_use
+            #forwarded
+            #inline
+            #vis fn #fmt_fn_name(#receiver, #param: core::fmt::Arguments<'_>) -> #output {
+                #record
+                self.#field_name = #value;
+                self
+            }
+        }
+    });
+    let maybe_setter = (attr.maybe || global_attr.maybe)
+        .then(|| {
+            if parsed.is_plain() {
+                if !attr.maybe {
+                    return Ok(quote! {});
+                }
+                return Err(syn::Error::new(
+                    field.ty.span(),
+                    "`maybe` only works on Option<T> fields",
+                ));
+            }
+            let maybe_fn_name = global_attr.method_name(format!("maybe_{}", fn_name));
+            let doc = format!(
+                " Sets `{}` to the given `Option`, clearing it if it is `None`.",
+                field_name
+            );
+            // The argument isn't converted even for `into` fields, as `None` would leave the
+            // compiler no way to infer the type to convert from.
+            Ok(quote! {
+                #[doc = #doc]
+                #must_use
+                #forwarded
+                #inline
+                #vis fn #maybe_fn_name(#receiver, #field_name: Option<#field_ty>) -> #output {
+                    #record
+                    self.#field_name = #field_name;
+                    self
+                }
+            })
+        })
+        .transpose()?;
+    let null_setter = attr.nullable.then(|| {
+        let null_fn_name = global_attr.method_name(format!("null_{}", fn_name));
+        let doc = format!(
+            " Sets `{}` to `Some(None)`, marking it as explicitly empty rather than unset.",
+            field_name
+        );
+        quote! {
+            #[doc = #doc]
+            #must_use
+            #forwarded
+            #inline
+            #vis fn #null_fn_name(#receiver) -> #output {
+                #record
+                self.#field_name = Some(None);
+                self
+            }
+        }
+    });
+    let flag_setters = attr
+        .flag
+        .then(|| {
+            let is_bool = match field_ty {
+                syn::Type::Path(ty) => ty.qself.is_none() && ty.path.is_ident("bool"),
+                _ => false,
+            };
+            if !is_bool {
+                return Err(syn::Error::new(
+                    field.ty.span(),
+                    "`flag` only works on bool fields, e.g. Option<bool>",
+                ));
+            }
+            let enable_fn_name = global_attr.method_name(format!("enable_{}", fn_name));
+            let disable_fn_name = global_attr.method_name(format!("disable_{}", fn_name));
+            let enable_doc = format!(" Sets `{}` to `true`.", field_name);
+            let disable_doc = format!(" Sets `{}` to `false`.", field_name);
+            let enabled = parsed.store(quote! { true });
+            let disabled = parsed.store(quote! { false });
+            Ok(quote! {
+                #[doc = #enable_doc]
+                #must_use
+                #forwarded
+                #inline
+                #vis fn #enable_fn_name(#receiver) -> #output {
+                    #record
+                    self.#field_name = #enabled;
+                    self
+                }
+                #[doc = #disable_doc]
+                #must_use
+                #forwarded
+                #inline
+                #vis fn #disable_fn_name(#receiver) -> #output {
+                    #record
+                    self.#field_name = #disabled;
+                    self
+                }
+            })
+        })
+        .transpose()?;
+    let take_method = (attr.take || global_attr.take)
+        .then(|| {
+            if parsed.is_plain() {
+                if !attr.take {
+                    return Ok(quote! {});
+                }
+                return Err(syn::Error::new(
+                    field.ty.span(),
+                    "`take` only works on Option<T> fields",
+                ));
+            }
+            let take_fn_name = global_attr.method_name(format!("take_{}", fn_name));
+            let doc = format!(
+                " Removes the value of `{}` and returns it, if it is set.",
+                field_name
+            );
+            Ok(quote! {
+                #[doc = #doc]
+                #forwarded
+                #inline
+                #vis fn #take_fn_name(&mut self) -> Option<#field_ty> {
+                    self.#field_name.take()
+                }
+            })
+        })
+        .transpose()?;
+    let clear_method = (attr.clear || global_attr.clear)
+        .then(|| {
+            if parsed.is_plain() {
+                if !attr.clear {
+                    return Ok(quote! {});
+                }
+                return Err(syn::Error::new(
+                    field.ty.span(),
+                    "`clear` only works on Option<T> fields",
+                ));
+            }
+            let clear_fn_name = global_attr.method_name(format!("clear_{}", fn_name));
+            let doc = format!(" Sets `{}` to `None`.", field_name);
+            Ok(quote! {
+                #[doc = #doc]
+                #must_use
+                #forwarded
+                #inline
+                #vis fn #clear_fn_name(#receiver) -> #output {
+                    #record
+                    self.#field_name = None;
+                    self
+                }
+            })
+        })
+        .transpose()?;
+    Ok(quote! {
+        #setter
+        #(#alias_setters)*
+        #dual_setter
+        #maybe_setter
+        #null_setter
+        #flag_setters
+        #take_method
+        #clear_method
+        #fmt_setter
+        #(#try_from_setters)*
+        #try_into_setter
+        #bitflags_setter
+        #add_setter
+        #each_setter
+        #insert_setter
+        #extend_setter
+        #base64_setter
+        #hex_setter
+        #humantime_setter
+        #duration_setters
+    })
+}
+
+/// Generate the usage example appended to the documentation of a field's builder method, if
+/// `#[build_it(doc_examples)]` is enabled. The example is marked `ignore`, as a doctest can't
+/// name the struct without knowing its module path.
+///
+/// # Example
+///
+/// For a field `age: Option<u32>` of `Person`, the generated documentation is:
+/// ````ignore
+/// /// # Example
+/// ///
+/// /// ```ignore
+/// /// let builder = Person::default().age(42);
+/// /// ```
+/// ````
+fn generate_doc_example(
+    name: &syn::Ident,
+    field: &ParsedField,
+    global_attr: &GlobalAttr,
+    has_docs: bool,
+) -> TokenStream {
+    if !global_attr.doc_examples {
+        return quote! {};
+    }
+    let fn_name = field.setter_name();
+    let value = example_value(field, global_attr);
+    let code = if field.is_mutable(global_attr) {
+        format!(
+            " let mut builder = {}::default();\n builder.{}({});",
+            name, fn_name, value
+        )
+    } else {
+        format!(" let builder = {}::default().{}({});", name, fn_name, value)
+    };
+    let separator = has_docs.then(|| quote! { #[doc = ""] });
+    quote! {
+        #separator
+        #[doc = " # Example"]
+        #[doc = ""]
+        #[doc = " ```ignore"]
+        #[doc = #code]
+        #[doc = " ```"]
+    }
+}
+
+/// An example argument for the builder method of a field in the generated usage example, based
+/// on the type of the field. Falls back to `Default::default()` for other types and arguments
+/// that are converted with a custom function.
+fn example_value(field: &ParsedField, global_attr: &GlobalAttr) -> String {
+    let attr = &field.attr;
+    let custom = attr.with.is_some()
+        || attr.map.is_some()
+        || attr.clone
+        || attr.nullable
+        || attr.shared_pointer().is_some();
+    let ty = get_inner_type(&field.field.ty).unwrap_or(&field.field.ty);
+    let ty_name = type_name(ty);
+    let converted = field.is_into(global_attr) || attr.as_ref_str || attr.to_string;
+    match ty_name.as_str() {
+        _ if custom => "Default::default()".to_string(),
+        "String" if converted => "\"text\"".to_string(),
+        "String" => "\"text\".to_string()".to_string(),
+        "PathBuf" if attr.as_ref_path => "\"path/to/file\"".to_string(),
+        "bool" => "true".to_string(),
+        "char" => "'a'".to_string(),
+        "f32" | "f64" => "1.5".to_string(),
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128"
+        | "isize" => "42".to_string(),
+        _ => "Default::default()".to_string(),
+    }
+}
+
+/// Generate the statement recording a call to one of the field's builder methods with the
+/// `metrics` crate, if `#[build_it(metrics)]` is enabled.
+fn generate_metrics(field: &ParsedField, global_attr: &GlobalAttr) -> TokenStream {
+    if !global_attr.metrics {
+        return quote! {};
+    }
+    let field_name = field.field.ident.as_ref().unwrap().to_string();
+    quote! {
+        ::metrics::counter!(
+            "build_it_setter_calls",
+            "builder" => core::any::type_name::<Self>(),
+            "field" => #field_name,
+        )
+        .increment(1);
+    }
+}
+
+/// Generate the builder method for a skipped field with `#[build_it(test_setter)]`.
+/// The method is only compiled for tests, or when the given feature is enabled.
+///
+/// # Example
+///
+/// For a field `#[build_it(skip, test_setter)] clock: Clock`, the generated method is:
+/// ```
+/// # struct Clock;
+/// # struct SimpleStruct {
+/// #    clock: Clock,
+/// # }
+/// # impl SimpleStruct {
+/// #[cfg(test)]
+/// pub fn clock(mut self, clock: Clock) -> Self {
+///    self.clock = clock;
+///    self
+/// }
+/// # }
+/// ```
+fn generate_test_setter(field: &ParsedField, global_attr: &GlobalAttr) -> TokenStream {
+    let Some(cfg) = &field.attr.test_setter else {
+        return quote! {};
+    };
+    let fn_name = field.setter_name();
+    let vis = field.vis();
+    let (receiver, output) = field.receiver(global_attr);
+    let must_use = field.must_use(global_attr);
+    let inline = global_attr.inline();
+    let docs = field.docs();
+    let doc_alias = field.doc_alias();
+    let forwarded = field.method_attrs(global_attr);
+    let field_name = field.field.ident.as_ref().unwrap();
+    let field_ty = get_inner_type(&field.field.ty).unwrap_or(&field.field.ty);
+    let param = field.param_name();
+    let value = field.store(quote! { #param });
+    quote! {
+        #(#docs)*
+        #doc_alias
+        #[cfg(#cfg)]
+        #must_use
+        #forwarded
+        #inline
+        #vis fn #fn_name(#receiver, #param: #field_ty) -> #output {
+            self.#field_name = #value;
+            self
+        }
+    }
+}
+
+#[derive(Default)]
+struct GlobalAttr {
+    builder: bool,
+    rename_all: Option<Case>,
+    vis: Option<syn::Visibility>,
+    suffix: Option<String>,
+    maybe: bool,
+    take: bool,
+    clear: bool,
+    validate: Option<syn::Path>,
+    typestate: bool,
+    mutable: bool,
+    dual: bool,
+    no_must_use: bool,
+    inline: Option<TokenStream>,
+    hidden: bool,
+    bound: Vec<syn::WherePredicate>,
+    into: bool,
+    describe: bool,
+    doc_examples: bool,
+    template: bool,
+    counters: bool,
+    set_json: bool,
+    gen_tests: bool,
+    shared: bool,
+    to_json_partial: bool,
+    into_parts: bool,
+    reset: bool,
+    with: bool,
+    merge_from: bool,
+    from_str: bool,
+    clap: bool,
+    metrics: bool,
+    presets: Vec<Preset>,
+}
+
+impl GlobalAttr {
+    /// Visibility of the generated methods, which are public unless `#[build_it(vis = "...")]` is
+    /// set.
+    fn vis(&self) -> syn::Visibility {
+        self.vis
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote! { pub })
+    }
+
+    /// The `#[must_use]` attribute for builder methods that return the modified builder, unless
+    /// disabled with `#[build_it(must_use = false)]`.
+    fn must_use(&self) -> TokenStream {
+        if self.no_must_use {
+            quote! {}
+        } else {
+            quote! { #[must_use] }
+        }
+    }
+
+    /// The `#[inline]` attribute for builder methods, if enabled with `#[build_it(inline)]` or
+    /// `#[build_it(inline(always))]`.
+    fn inline(&self) -> TokenStream {
+        self.inline.clone().unwrap_or_default()
+    }
+
+    /// The generics of the struct with the predicates of `#[build_it(bound = "...")]` added to
+    /// the where clause, for the generated impls.
+    fn bounded_generics(&self, generics: &syn::Generics) -> syn::Generics {
+        let mut generics = generics.clone();
+        if !self.bound.is_empty() {
+            generics
+                .make_where_clause()
+                .predicates
+                .extend(self.bound.iter().cloned());
+        }
+        generics
+    }
+
+    /// Allow non-snake-case field names in the generated code, which `rename_all` is meant for.
+    fn allow_case(&self) -> TokenStream {
+        match self.rename_all {
+            Some(_) => quote! { #[allow(non_snake_case)] },
+            None => quote! {},
+        }
+    }
+
+    /// Name of a builder method made from the setter name, like `maybe_name`, converted as a
+    /// whole to the `rename_all` convention.
+    fn method_name(&self, name: String) -> syn::Ident {
+        let name = match self.rename_all {
+            Some(case) => case.apply(&name),
+            None => name,
+        };
+        syn::Ident::new(&name, Span::call_site())
+    }
+}
+
+/// Parse the predicates of a `bound = "..."` attribute, e.g. `T: Clone, U: Send`.
+fn parse_bound(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Vec<syn::WherePredicate>> {
+    let lit: syn::LitStr = meta.value()?.parse()?;
+    let bound = lit.parse_with(
+        syn::punctuated::Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated,
+    )?;
+    Ok(bound.into_iter().collect())
+}
+
+/// A named preset declared with `#[build_it(preset(name = "...", field = value, ...))]`.
+struct Preset {
+    name: syn::Ident,
+    values: Vec<(syn::Ident, syn::Expr)>,
+}
+
+fn parse_global_attr(input: &DeriveInput) -> syn::Result<GlobalAttr> {
+    let mut result = GlobalAttr::default();
+    let attrs = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("build_it"));
+    for attr in attrs {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("into") {
+                result.into = true;
+            } else if meta.path.is_ident("builder") {
+                result.builder = true;
+            } else if meta.path.is_ident("validate") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.validate = Some(lit.parse()?);
+            } else if meta.path.is_ident("maybe") {
+                result.maybe = true;
+            } else if meta.path.is_ident("take") {
+                result.take = true;
+            } else if meta.path.is_ident("clear") {
+                result.clear = true;
+            } else if meta.path.is_ident("rename_all") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.rename_all = Some(Case::parse(&lit)?);
+            } else if meta.path.is_ident("vis") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.vis = Some(lit.parse()?);
+            } else if meta.path.is_ident("inline") {
+                if meta.input.peek(syn::token::Paren) {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let hint: syn::Ident = content.parse()?;
+                    if hint != "always" {
+                        return Err(syn::Error::new(hint.span(), "expected `always`"));
+                    }
+                    result.inline = Some(quote! { #[inline(#hint)] });
+                } else {
+                    result.inline = Some(quote! { #[inline] });
+                }
+            } else if meta.path.is_ident("mutable") {
+                result.mutable = true;
+            } else if meta.path.is_ident("dual") {
+                result.dual = true;
+            } else if meta.path.is_ident("must_use") {
+                let lit: syn::LitBool = meta.value()?.parse()?;
+                result.no_must_use = !lit.value;
+            } else if meta.path.is_ident("hidden") {
+                result.hidden = true;
+            } else if meta.path.is_ident("bound") {
+                result.bound.extend(parse_bound(&meta)?);
+            } else if meta.path.is_ident("suffix") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.suffix = Some(lit.value());
+            } else if meta.path.is_ident("typestate") {
+                result.typestate = true;
+            } else if meta.path.is_ident("describe") {
+                result.describe = true;
+            } else if meta.path.is_ident("doc_examples") {
+                result.doc_examples = true;
+            } else if meta.path.is_ident("template") {
+                result.template = true;
+            } else if meta.path.is_ident("counters") {
+                result.counters = true;
+            } else if meta.path.is_ident("metrics") {
+                if !cfg!(feature = "metrics") {
+                    return Err(meta.error("`metrics` requires the `metrics` feature"));
+                }
+                result.metrics = true;
+            } else if meta.path.is_ident("shared") {
+                result.shared = true;
+            } else if meta.path.is_ident("into_parts") {
+                result.into_parts = true;
+            } else if meta.path.is_ident("reset") {
+                result.reset = true;
+            } else if meta.path.is_ident("with") {
+                result.with = true;
+            } else if meta.path.is_ident("merge_from") {
+                result.merge_from = true;
+            } else if meta.path.is_ident("from_str") {
+                result.from_str = true;
+            } else if meta.path.is_ident("gen_tests") {
+                result.gen_tests = true;
+            } else if meta.path.is_ident("clap") {
+                if !cfg!(feature = "clap") {
+                    return Err(meta.error("`clap` requires the `clap` feature"));
+                }
+                result.clap = true;
+            } else if meta.path.is_ident("to_json_partial") {
+                if !cfg!(feature = "serde_json") {
+                    return Err(meta.error("`to_json_partial` requires the `serde_json` feature"));
+                }
+                result.to_json_partial = true;
+            } else if meta.path.is_ident("set_json") {
+                if !cfg!(feature = "serde_json") {
+                    return Err(meta.error("`set_json` requires the `serde_json` feature"));
+                }
+                result.set_json = true;
+            } else if meta.path.is_ident("preset") {
+                result.presets.push(parse_preset(&meta)?);
+            }
+            Ok(())
+        })?;
+    }
+    Ok(result)
+}
+
+fn parse_preset(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Preset> {
+    let mut name = None;
+    let mut values = Vec::new();
+    meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("name") {
+            let lit: syn::LitStr = meta.value()?.parse()?;
+            name = Some(syn::Ident::new(&lit.value(), lit.span()));
+        } else if let Some(field_name) = meta.path.get_ident() {
+            let value: syn::Expr = meta.value()?.parse()?;
+            values.push((field_name.clone(), value));
+        }
+        Ok(())
+    })?;
+    let name = name.ok_or_else(|| meta.error("A preset requires a name"))?;
+    Ok(Preset { name, values })
+}
+
+/// The value a field falls back to when it is not set.
+enum FieldDefault {
+    /// `#[build_it(default)]`: the `Default` value of the type.
+    Default,
+    /// `#[build_it(default_fn = "...")]`: the return value of the given function.
+    Fn(syn::Path),
+}
+
+#[derive(Default)]
+struct Attr {
+    skip: bool,
+    required: bool,
+    default: Option<FieldDefault>,
+    validate: Option<syn::Path>,
+    into: Option<bool>,
+    no_docs: bool,
+    doc: Vec<syn::Attribute>,
+    bound: Vec<syn::WherePredicate>,
+    hidden: bool,
+    rename: Option<String>,
+    alias: Vec<syn::Ident>,
+    vis: Option<syn::Visibility>,
+    mutable: Option<bool>,
+    dual: bool,
+    try_from: Vec<syn::Type>,
+    try_into: bool,
+    to_string: bool,
+    as_ref_path: bool,
+    as_ref_str: bool,
+    clone: bool,
+    boxed: bool,
+    with: Option<syn::Path>,
+    arg: Option<syn::Type>,
+    map: Option<syn::ExprClosure>,
+    arc: bool,
+    rc: bool,
+    bitflags: Option<syn::Ident>,
+    add: Option<syn::Ident>,
+    each: Option<syn::Ident>,
+    insert: Option<Option<syn::Ident>>,
+    extend: bool,
+    maybe: bool,
+    nullable: bool,
+    take: bool,
+    clear: bool,
+    flag: bool,
+    base64: bool,
+    hex: bool,
+    humantime: bool,
+    duration: bool,
+    fmt: bool,
+    test_setter: Option<TokenStream>,
+    sensitive: bool,
+}
+
+impl Attr {
+    /// The attributes that generate builder methods besides the setter and `null_*`, with whether
+    /// they are set.
+    fn extra_methods(&self) -> [(&'static str, bool); 18] {
+        [
+            ("alias", !self.alias.is_empty()),
+            ("dual", self.dual),
+            ("each", self.each.is_some()),
+            ("insert", self.insert.is_some()),
+            ("extend", self.extend),
+            ("maybe", self.maybe),
+            ("flag", self.flag),
+            ("take", self.take),
+            ("clear", self.clear),
+            ("try_into", self.try_into),
+            ("try_from", !self.try_from.is_empty()),
+            ("duration", self.duration),
+            ("humantime", self.humantime),
+            ("base64", self.base64),
+            ("hex", self.hex),
+            ("fmt", self.fmt),
+            ("add", self.add.is_some()),
+            ("bitflags", self.bitflags.is_some()),
+        ]
+    }
+
+    /// The shared pointer type that the builder methods wrap their argument in, if any.
+    fn shared_pointer(&self) -> Option<&'static str> {
+        if self.arc {
+            Some("Arc")
+        } else if self.rc {
+            Some("Rc")
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_attr(field: &syn::Field) -> syn::Result<Attr> {
+    let mut result = Attr::default();
+    let attrs = field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("build_it"));
+    for attr in attrs {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                result.skip = true;
+            } else if meta.path.is_ident("required") {
+                result.required = true;
+            } else if meta.path.is_ident("default") {
+                result.default = Some(FieldDefault::Default);
+            } else if meta.path.is_ident("validate") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.validate = Some(lit.parse()?);
+            } else if meta.path.is_ident("default_fn") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.default = Some(FieldDefault::Fn(lit.parse()?));
+            } else if meta.path.is_ident("into") {
+                result.into = if meta.input.peek(syn::Token![=]) {
+                    let lit: syn::LitBool = meta.value()?.parse()?;
+                    Some(lit.value)
+                } else {
+                    Some(true)
+                };
+            } else if meta.path.is_ident("no_docs") {
+                result.no_docs = true;
+            } else if meta.path.is_ident("doc") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.doc.push(syn::parse_quote!(#[doc = #lit]));
+            } else if meta.path.is_ident("bound") {
+                result.bound.extend(parse_bound(&meta)?);
+            } else if meta.path.is_ident("hidden") {
+                result.hidden = true;
+            } else if meta.path.is_ident("rename") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.rename = Some(lit.value());
+            } else if meta.path.is_ident("alias") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.alias.push(lit.parse()?);
+            } else if meta.path.is_ident("vis") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.vis = Some(lit.parse()?);
+            } else if meta.path.is_ident("by_ref") {
+                result.mutable = Some(true);
+            } else if meta.path.is_ident("by_value") {
+                result.mutable = Some(false);
+            } else if meta.path.is_ident("dual") {
+                result.dual = true;
+            } else if meta.path.is_ident("try_from") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.try_from.push(lit.parse()?);
+            } else if meta.path.is_ident("try_into") {
+                result.try_into = true;
+            } else if meta.path.is_ident("to_string") {
+                result.to_string = true;
+            } else if meta.path.is_ident("as_ref_path") {
+                result.as_ref_path = true;
+            } else if meta.path.is_ident("as_ref_str") {
+                result.as_ref_str = true;
+            } else if meta.path.is_ident("clone") {
+                result.clone = true;
+            } else if meta.path.is_ident("boxed") {
+                result.boxed = true;
+            } else if meta.path.is_ident("with") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.with = Some(lit.parse()?);
+            } else if meta.path.is_ident("map") {
+                let map: syn::ExprClosure = meta.value()?.parse()?;
+                let typed = matches!(map.inputs.first(), Some(syn::Pat::Type(_)));
+                if map.inputs.len() != 1 || !typed {
+                    return Err(syn::Error::new(
+                        map.inputs.span(),
+                        "expected a closure with one argument with a type annotation, e.g. `|s: &str| s.trim().to_owned()`",
+                    ));
+                }
+                result.map = Some(map);
+            } else if meta.path.is_ident("arg") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.arg = Some(lit.parse()?);
+            } else if meta.path.is_ident("arc") {
+                result.arc = true;
+            } else if meta.path.is_ident("rc") {
+                result.rc = true;
+            } else if meta.path.is_ident("bitflags") {
+                if !cfg!(feature = "bitflags") {
+                    return Err(meta.error("`bitflags` requires the `bitflags` feature"));
+                }
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.bitflags = Some(syn::Ident::new(&lit.value(), lit.span()));
+            } else if meta.path.is_ident("each") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.each = Some(syn::Ident::new(&lit.value(), lit.span()));
+            } else if meta.path.is_ident("insert") {
+                result.insert = Some(if meta.input.peek(syn::Token![=]) {
+                    let lit: syn::LitStr = meta.value()?.parse()?;
+                    Some(syn::Ident::new(&lit.value(), lit.span()))
+                } else {
+                    None
+                });
+            } else if meta.path.is_ident("maybe") {
+                result.maybe = true;
+            } else if meta.path.is_ident("nullable") {
+                result.nullable = true;
+            } else if meta.path.is_ident("take") {
+                result.take = true;
+            } else if meta.path.is_ident("clear") {
+                result.clear = true;
+            } else if meta.path.is_ident("flag") {
+                result.flag = true;
+            } else if meta.path.is_ident("extend") {
+                result.extend = true;
+            } else if meta.path.is_ident("add") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.add = Some(syn::Ident::new(&lit.value(), lit.span()));
+            } else if meta.path.is_ident("fmt") {
+                result.fmt = true;
+            } else if meta.path.is_ident("sensitive") {
+                result.sensitive = true;
+            } else if meta.path.is_ident("test_setter") {
+                result.test_setter = Some(if meta.input.peek(syn::Token![=]) {
+                    let feature: syn::LitStr = meta.value()?.parse()?;
+                    quote! { any(test, feature = #feature) }
+                } else {
+                    quote! { test }
+                });
+            } else if meta.path.is_ident("base64") {
+                if !cfg!(feature = "base64") {
+                    return Err(meta.error("`base64` requires the `base64` feature"));
+                }
+                result.base64 = true;
+            } else if meta.path.is_ident("hex") {
+                if !cfg!(feature = "hex") {
+                    return Err(meta.error("`hex` requires the `hex` feature"));
+                }
+                result.hex = true;
+            } else if meta.path.is_ident("humantime") {
+                if !cfg!(feature = "humantime") {
+                    return Err(meta.error("`humantime` requires the `humantime` feature"));
+                }
+                result.humantime = true;
+            } else if meta.path.is_ident("duration") {
+                result.duration = true;
+            }
+            Ok(())
+        })?;
+    }
+    let skipped = result.skip || field.attrs.iter().any(|attr| attr.path().is_ident("skip"));
+    if result.test_setter.is_some() && !skipped {
+        return Err(syn::Error::new(
+            field.span(),
+            "`test_setter` only applies to fields with `#[build_it(skip)]`",
+        ));
+    }
+    if let (Some(arg), None) = (&result.arg, &result.with) {
+        return Err(syn::Error::new(
+            arg.span(),
+            "`arg` only applies to fields with `#[build_it(with = \"...\")]`",
+        ));
+    }
+    if result.required && result.default.is_some() {
+        return Err(syn::Error::new(
+            field.span(),
+            "`required` can't be combined with `default` or `default_fn`",
+        ));
+    }
+    let value_ty = get_inner_type(&field.ty).unwrap_or(&field.ty);
+    if result.nullable && get_inner_type(value_ty).is_none() {
+        return Err(syn::Error::new(
+            field.ty.span(),
+            "`nullable` only works on Option<Option<T>> fields",
+        ));
+    }
+    if result.boxed && boxed_argument(value_ty).is_none() {
+        return Err(syn::Error::new(
+            field.ty.span(),
+            "`boxed` only works on Box<T> and Pin<Box<T>> fields",
+        ));
+    }
+    if let Some(wrapper) = result.shared_pointer() {
+        if get_wrapped_type(value_ty, wrapper).is_none() {
+            return Err(syn::Error::new(
+                field.ty.span(),
+                format!(
+                    "`{}` only works on {}<T> fields",
+                    wrapper.to_lowercase(),
+                    wrapper
+                ),
+            ));
+        }
+    }
+    Ok(result)
+}
+
+/// Iterate over the fields that get a builder method, along with the type the method takes: the
+/// type inside their `Option`, or the field type for required fields that aren't an `Option`.
+fn buildable_fields<'a>(
+    fields: &'a [ParsedField<'a>],
+) -> impl Iterator<Item = (&'a ParsedField<'a>, &'a syn::Type)> {
+    fields.iter().filter_map(|field| {
+        if is_skipped(field) {
+            return None;
+        }
+        match get_inner_type(&field.field.ty) {
+            Some(ty) => Some((field, ty)),
+            None if field.attr.required || field.attr.default.is_some() => {
+                Some((field, &field.field.ty))
+            }
+            None => None,
+        }
+    })
+}
+
+/// Whether a type is one of the type parameters of the struct, e.g. `T` in `struct Foo<T>`.
+fn is_type_param(ty: &syn::Type, generics: &syn::Generics) -> bool {
+    let syn::Type::Path(type_path) = ty else {
+        return false;
+    };
+    let Some(ident) = type_path.path.get_ident() else {
+        return false;
+    };
+    type_path.qself.is_none() && generics.type_params().any(|param| param.ident == *ident)
+}
+
+/// Whether no builder method is generated for a field, either because of `#[build_it(skip)]` or
+/// the deprecated `#[skip]` attribute.
+fn is_skipped(field: &ParsedField) -> bool {
+    field.attr.skip
+        || field
+            .field
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("skip"))
+}
+
+/// Get the element type of a collection type like `Vec<T>`: its first generic type argument.
+fn get_element_type(ty: &syn::Type) -> Option<&syn::Type> {
+    if let syn::Type::Path(ref type_path) = ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            if let syn::PathArguments::AngleBracketed(ref args) = segment.arguments {
+                return args.args.iter().find_map(|arg| match arg {
+                    syn::GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Get the key and value types of a map type like `HashMap<K, V>`: its first two generic type
+/// arguments.
+fn get_map_types(ty: &syn::Type) -> Option<(&syn::Type, &syn::Type)> {
+    let syn::Type::Path(ref type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    let syn::PathArguments::AngleBracketed(ref args) = segment.arguments else {
+        return None;
+    };
+    let mut types = args.args.iter().filter_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    });
+    Some((types.next()?, types.next()?))
+}
+
+/// Get the inner type of an Option<T> type.
+fn get_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    if let syn::Type::Path(ref type_path) = ty {
+        if let Some(segment) = type_path.path.segments.first() {
+            // Check if the type is an Option
+            if segment.ident == "Option" {
+                // Get the type inside the Option: the first generic argument
+                if let syn::PathArguments::AngleBracketed(ref args) = segment.arguments {
+                    if let Some(syn::GenericArgument::Type(ref ty)) = args.args.first() {
+                        return Some(ty);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Check if a type is a `Cow<'_, T>`.
+fn is_cow(ty: &syn::Type) -> bool {
+    let syn::Type::Path(ref type_path) = ty else {
+        return false;
+    };
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "Cow")
+}
+
+/// Get the argument type of a builder method that boxes its argument for a `Box<T>` or
+/// `Pin<Box<T>>` field, and the function boxing it. The argument type is `T`, or
+/// `impl Trait + 'static` for a trait object `Box<dyn Trait>`.
+fn boxed_argument(ty: &syn::Type) -> Option<(TokenStream, TokenStream)> {
+    let (inner, constructor) = match get_wrapped_type(ty, "Pin") {
+        Some(boxed) => (
+            get_wrapped_type(boxed, "Box")?,
+            quote! { ::std::boxed::Box::pin },
+        ),
+        None => (
+            get_wrapped_type(ty, "Box")?,
+            quote! { ::std::boxed::Box::new },
+        ),
+    };
+    let syn::Type::TraitObject(object) = inner else {
+        return Some((quote! { #inner }, constructor));
+    };
+    let bounds = &object.bounds;
+    let has_lifetime = bounds
+        .iter()
+        .any(|bound| matches!(bound, syn::TypeParamBound::Lifetime(_)));
+    if has_lifetime {
+        Some((quote! { impl #bounds }, constructor))
+    } else {
+        Some((quote! { impl #bounds + 'static }, constructor))
+    }
+}
+
+/// Check if a type is a boxed closure like `Box<dyn Fn(Request) -> Response + Send>` or a pinned
+/// future like `Pin<Box<dyn Future<Output = T> + Send>>`, whose builder method boxes its argument
+/// without `#[build_it(boxed)]`.
+fn is_boxed_callback(ty: &syn::Type) -> bool {
+    let (boxed, traits) = match get_wrapped_type(ty, "Pin") {
+        Some(boxed) => (boxed, &["Future"][..]),
+        None => (ty, &["Fn", "FnMut", "FnOnce"][..]),
+    };
+    let Some(syn::Type::TraitObject(object)) = get_wrapped_type(boxed, "Box") else {
+        return false;
+    };
+    object.bounds.iter().any(|bound| match bound {
+        syn::TypeParamBound::Trait(bound) => bound
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| traits.iter().any(|name| segment.ident == name)),
+        _ => false,
+    })
+}
+
+/// Get the type inside a wrapper type like `Arc<T>` with the given name: its first generic type
+/// argument.
+fn get_wrapped_type<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    let syn::Type::Path(ref type_path) = ty else {
+        return None;
+    };
+    if type_path.path.segments.last()?.ident != wrapper {
+        return None;
+    }
+    get_element_type(ty)
+}
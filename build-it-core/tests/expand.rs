@@ -0,0 +1,494 @@
+use syn::parse_quote;
+
+#[test]
+fn expand_struct() {
+    let input = parse_quote! {
+        struct SimpleStruct {
+            name: Option<String>,
+            #[build_it(rename = "years")]
+            age: Option<u32>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("pub fn name"));
+    assert!(tokens.contains("pub fn years"));
+}
+
+#[test]
+fn expand_errors() {
+    let input = parse_quote! {
+        enum SimpleEnum {
+            A,
+        }
+    };
+    let err = build_it_core::expand(&input).unwrap_err();
+    assert_eq!(err.to_string(), "Builder derive does not work on enums");
+
+    let input = parse_quote! {
+        struct InvalidAttribute {
+            #[build_it(rename = 42)]
+            name: Option<String>,
+        }
+    };
+    assert!(build_it_core::expand(&input).is_err());
+}
+
+#[test]
+fn generic_into() {
+    let input = parse_quote! {
+        #[build_it(into)]
+        struct Container<T> {
+            label: Option<String>,
+            value: Option<T>,
+        }
+    };
+    let err = build_it_core::expand(&input).unwrap_err();
+    assert!(err.to_string().contains("generic type `T`"));
+    assert!(err.to_string().contains("#[build_it(into = false)]"));
+
+    let input = parse_quote! {
+        #[build_it(into)]
+        struct Container<T> {
+            label: Option<String>,
+            #[build_it(into = false)]
+            value: Option<T>,
+            items: Option<Vec<T>>,
+        }
+    };
+    assert!(build_it_core::expand(&input).is_ok());
+}
+
+#[test]
+fn forward_doc_alias() {
+    let input = parse_quote! {
+        struct Request {
+            /// Deadline of the request
+            #[doc(alias = "timeout")]
+            request_deadline: Option<u64>,
+            /// Implementation note
+            #[doc(alias = "retries")]
+            #[build_it(no_docs)]
+            max_attempts: Option<u32>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains(r#"# [doc (alias = "timeout")] # [must_use] pub fn request_deadline"#));
+    assert!(tokens.contains(r#"# [doc (alias = "retries")] # [must_use] pub fn max_attempts"#));
+    assert!(!tokens.contains("Implementation note"));
+}
+
+#[test]
+fn rename_doc_alias() {
+    let input = parse_quote! {
+        struct Person {
+            #[build_it(rename = "years")]
+            age: Option<u32>,
+            #[build_it(rename = "name")]
+            name: Option<String>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains(r#"# [doc (alias = "age")] # [must_use] pub fn years"#));
+    assert!(!tokens.contains(r#"alias = "name""#));
+}
+
+#[test]
+fn custom_doc() {
+    let input = parse_quote! {
+        struct Person {
+            /// Name of the person
+            #[doc(alias = "full_name")]
+            #[build_it(doc = "Sets the name of the person.", doc = "")]
+            #[build_it(doc = "The name is shown in greetings.")]
+            name: Option<String>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains(
+        r#"# [doc = "Sets the name of the person."] # [doc = ""] # [doc = "The name is shown in greetings."] # [doc (alias = "full_name")] # [must_use] pub fn name"#
+    ));
+    assert!(!tokens.contains("Name of the person"));
+}
+
+#[test]
+fn doc_examples() {
+    let input = parse_quote! {
+        #[build_it(doc_examples)]
+        struct Person {
+            /// Name of the person
+            #[build_it(into)]
+            name: Option<String>,
+            #[build_it(by_ref)]
+            age: Option<u32>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains(
+        r#"Name of the person"] # [doc = ""] # [doc = " # Example"] # [doc = ""] # [doc = " ```ignore"] # [doc = " let builder = Person::default().name(\"text\");"] # [doc = " ```"]"#
+    ));
+    assert!(tokens.contains(
+        r#"# [doc = " # Example"] # [doc = ""] # [doc = " ```ignore"] # [doc = " let mut builder = Person::default();\n builder.age(42);"] # [doc = " ```"] pub fn age"#
+    ));
+}
+
+#[test]
+fn must_use() {
+    let input = parse_quote! {
+        struct Request {
+            timeout: Option<u64>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("# [must_use] pub fn timeout"));
+
+    let input = parse_quote! {
+        #[build_it(must_use = false)]
+        struct Request {
+            timeout: Option<u64>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(!tokens.contains("must_use"));
+}
+
+#[test]
+fn inline() {
+    let input = parse_quote! {
+        #[build_it(inline)]
+        struct Point {
+            x: Option<f64>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("# [inline] pub fn x"));
+
+    let input = parse_quote! {
+        #[build_it(inline(always))]
+        struct Point {
+            x: Option<f64>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("# [inline (always)] pub fn x"));
+
+    let input = parse_quote! {
+        #[build_it(inline(never))]
+        struct Point {
+            x: Option<f64>,
+        }
+    };
+    assert!(build_it_core::expand(&input).is_err());
+}
+
+#[test]
+fn forward_deprecated() {
+    let input = parse_quote! {
+        struct Client {
+            #[deprecated(note = "use `timeout` instead")]
+            #[build_it(take)]
+            deadline: Option<u64>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    let deprecated = r#"# [deprecated (note = "use `timeout` instead")]"#;
+    assert!(tokens.contains(&format!("{} pub fn deadline", deprecated)));
+    assert!(tokens.contains(&format!("{} pub fn take_deadline", deprecated)));
+}
+
+#[test]
+fn hidden() {
+    let input = parse_quote! {
+        struct Request {
+            #[build_it(hidden, clear)]
+            trace_id: Option<u64>,
+            timeout: Option<u64>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("# [must_use] # [doc (hidden)] pub fn trace_id"));
+    assert!(tokens.contains("# [must_use] # [doc (hidden)] pub fn clear_trace_id"));
+    assert!(!tokens.contains("# [must_use] # [doc (hidden)] pub fn timeout"));
+
+    let input = parse_quote! {
+        #[build_it(hidden)]
+        struct Request {
+            timeout: Option<u64>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("# [must_use] # [doc (hidden)] pub fn timeout"));
+}
+
+#[test]
+fn to_json_partial_keys() {
+    let input = parse_quote! {
+        #[build_it(to_json_partial)]
+        struct Patch {
+            #[serde(rename = "fullName", skip_serializing_if = "Option::is_none")]
+            name: Option<String>,
+            #[serde(default, rename(serialize = "years", deserialize = "age"))]
+            age: Option<u32>,
+            email: Option<String>,
+            #[build_it(sensitive)]
+            password: Option<String>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains(r#""fullName" . to_string ()"#));
+    assert!(tokens.contains(r#""years" . to_string ()"#));
+    assert!(tokens.contains(r#""email" . to_string ()"#));
+    assert!(!tokens.contains(r#""password" . to_string ()"#));
+}
+
+#[test]
+fn typestate_unsupported_options() {
+    for option in [
+        "describe",
+        "preset(name = \"prod\", retries = 3)",
+        "counters",
+        "reset",
+        "merge_from",
+        "from_str",
+        "shared",
+        "gen_tests",
+        "with",
+        "template",
+        "into_parts",
+    ] {
+        let input = syn::parse_str(&format!(
+            "#[build_it(typestate, {})] struct Job {{ retries: Option<u32> }}",
+            option
+        ))
+        .unwrap();
+        let err = build_it_core::expand(&input).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("cannot be used with `#[build_it(typestate)]`"),
+            "{}: {}",
+            option,
+            err
+        );
+    }
+}
+
+#[test]
+fn typestate_unsupported_field_attributes() {
+    for (attr, ty) in [
+        ("alias = \"tag\"", "Option<String>"),
+        ("nullable", "Option<Option<String>>"),
+        ("each = \"tag\"", "Option<Vec<String>>"),
+        (
+            "insert",
+            "Option<std::collections::HashMap<String, String>>",
+        ),
+        ("extend", "Option<Vec<String>>"),
+        ("maybe", "Option<String>"),
+        ("flag", "Option<bool>"),
+        ("take", "Option<String>"),
+        ("clear", "Option<String>"),
+        ("try_into", "Option<u8>"),
+        ("try_from = \"u64\"", "Option<u8>"),
+        ("duration", "Option<std::time::Duration>"),
+        ("fmt", "Option<String>"),
+        ("skip, test_setter", "String"),
+        ("add = \"add_tag\"", "Option<Vec<String>>"),
+    ] {
+        let input = syn::parse_str(&format!(
+            "#[build_it(typestate)] struct Job {{ #[build_it({})] tags: {} }}",
+            attr, ty
+        ))
+        .unwrap();
+        let err = build_it_core::expand(&input).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("cannot be used with `#[build_it(typestate)]`"),
+            "{}: {}",
+            attr,
+            err
+        );
+    }
+}
+
+#[test]
+fn set_json_and_from_str_keys() {
+    let input = parse_quote! {
+        #[build_it(set_json, from_str)]
+        struct Storage {
+            #[build_it(rename = "capacity")]
+            size: Option<u64>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert_eq!(tokens.matches(r#""capacity" =>"#).count(), 2);
+    assert!(!tokens.contains(r#""size" =>"#));
+}
+
+#[test]
+fn shared_unsupported_attributes() {
+    for (attr, ty) in [
+        ("alias = \"tag\"", "Option<String>"),
+        ("dual", "Option<String>"),
+        ("each = \"tag\"", "Option<Vec<String>>"),
+        ("maybe", "Option<String>"),
+        ("flag", "Option<bool>"),
+        ("take", "Option<String>"),
+        ("try_into", "Option<u8>"),
+        ("fmt", "Option<String>"),
+    ] {
+        let input = syn::parse_str(&format!(
+            "#[build_it(shared)] struct Job {{ #[build_it({})] tags: {} }}",
+            attr, ty
+        ))
+        .unwrap();
+        let err = build_it_core::expand(&input).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("cannot be used with `#[build_it(shared)]`"),
+            "{}: {}",
+            attr,
+            err
+        );
+    }
+}
+
+#[test]
+fn builder_test_setter() {
+    let input = parse_quote! {
+        #[build_it(builder)]
+        struct Ts {
+            #[build_it(skip, test_setter)]
+            hidden: u32,
+        }
+    };
+    let err = build_it_core::expand(&input).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("`test_setter` cannot be used with `#[build_it(builder)]`"));
+}
+
+#[test]
+fn invalid_attributes() {
+    for (input, message) in [
+        (
+            "struct Job { name: String }",
+            "Builder only works on Option<T> fields",
+        ),
+        (
+            "struct Job { #[build_it(add = \"add_tag\")] tag: Option<String> }",
+            "`add` only works on collections",
+        ),
+        (
+            "struct Job { #[build_it(each = \"tag\")] tag: Option<String> }",
+            "`each` only works on collections",
+        ),
+        (
+            "struct Job { #[build_it(insert)] tag: Option<String> }",
+            "`insert` only works on maps",
+        ),
+        (
+            "struct Job { #[build_it(required, maybe)] name: String }",
+            "`maybe` only works on Option<T> fields",
+        ),
+        (
+            "struct Job { #[build_it(required, take)] name: String }",
+            "`take` only works on Option<T> fields",
+        ),
+        (
+            "struct Job { #[build_it(required, clear)] name: String }",
+            "`clear` only works on Option<T> fields",
+        ),
+        (
+            "struct Job { #[build_it(flag)] verbose: Option<u8> }",
+            "`flag` only works on bool fields",
+        ),
+        (
+            "struct Job { #[build_it(required, default)] name: Option<String> }",
+            "`required` can't be combined with `default` or `default_fn`",
+        ),
+        (
+            "struct Job { #[build_it(required, default_fn = \"name\")] name: Option<String> }",
+            "`required` can't be combined with `default` or `default_fn`",
+        ),
+        (
+            "#[build_it(gen_tests)] struct Job<T> { value: Option<T> }",
+            "`gen_tests` does not support generic structs",
+        ),
+        (
+            "#[build_it(gen_tests)] struct Job { run: Option<Box<dyn Fn() + Send>> }",
+            "`gen_tests` can't compare the values of boxed fields",
+        ),
+        (
+            "#[build_it(from_str)] struct Job { run: Option<Box<dyn Fn() + Send>> }",
+            "`from_str` can't parse the values of boxed fields",
+        ),
+    ] {
+        let input = syn::parse_str(input).unwrap();
+        let err = build_it_core::expand(&input).unwrap_err();
+        assert!(err.to_string().starts_with(message), "{}", err);
+    }
+}
+
+#[test]
+fn base64_hex() {
+    let input = parse_quote! {
+        struct Credentials {
+            #[build_it(base64, hex)]
+            key: Option<Vec<u8>>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("pub fn key_base64"));
+    assert!(tokens.contains(":: base64 :: engine :: general_purpose :: STANDARD . decode"));
+    assert!(tokens.contains("pub fn key_hex"));
+    assert!(tokens.contains(":: hex :: decode"));
+}
+
+#[test]
+fn metrics() {
+    let input = parse_quote! {
+        #[build_it(metrics)]
+        struct ServerConfig {
+            workers: Option<u32>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains(r#":: metrics :: counter ! ("build_it_setter_calls""#));
+    assert!(tokens.contains(r#""field" => "workers""#));
+}
+
+#[test]
+fn clap_args() {
+    let input = parse_quote! {
+        #[build_it(clap)]
+        struct ServerConfig {
+            /// Number of worker threads
+            workers: Option<u32>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("# [derive (:: clap :: Args , Debug , Clone , Default)]"));
+    assert!(tokens.contains("# [arg (long)] pub workers : Option < u32 >"));
+    assert!(tokens.contains("impl core :: convert :: From < ServerConfigArgs > for ServerConfig"));
+
+    let input = parse_quote! {
+        #[build_it(clap)]
+        struct ServerConfig<T> {
+            workers: Option<T>,
+        }
+    };
+    let err = build_it_core::expand(&input).unwrap_err();
+    assert_eq!(err.to_string(), "`clap` does not support generic structs");
+}
+
+#[test]
+fn humantime() {
+    let input = parse_quote! {
+        struct Client {
+            #[build_it(humantime)]
+            timeout: Option<std::time::Duration>,
+        }
+    };
+    let tokens = build_it_core::expand(&input).unwrap().to_string();
+    assert!(tokens.contains("pub fn timeout_str"));
+    assert!(tokens.contains(":: humantime :: parse_duration"));
+}
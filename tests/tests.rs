@@ -1,5 +1,31 @@
 use build_it::Builder;
 
+// Generated tests are only collected from module-level structs.
+#[derive(Default, Builder)]
+#[build_it(gen_tests)]
+struct GenTests {
+    name: Option<String>,
+    #[build_it(rename = "years", into)]
+    age: Option<u32>,
+    tags: Option<Vec<String>>,
+    #[build_it(skip)]
+    _internal: u32,
+}
+
+#[derive(Default, Builder)]
+#[build_it(gen_tests, mutable)]
+struct GenTestsMutable {
+    name: Option<String>,
+    #[build_it(by_value, clone)]
+    tags: Option<Vec<String>>,
+    #[build_it(nullable)]
+    parent: Option<Option<String>>,
+    #[build_it(arc)]
+    config: Option<std::sync::Arc<String>>,
+    #[build_it(map = |port: u16| u32::from(port))]
+    port: Option<u32>,
+}
+
 #[test]
 fn pub_fields() {
     #[derive(Default, Builder)]
@@ -36,6 +62,99 @@ fn lifetimes() {
     assert_eq!(builder.age, Some(30));
 }
 
+#[test]
+fn bounded_generics() {
+    #[derive(Default, Builder)]
+    #[build_it(describe)]
+    struct Bounded<'a, 'b: 'a, T: Clone>
+    where
+        T: Send,
+    {
+        name: Option<&'a str>,
+        parent: Option<&'b str>,
+        value: Option<T>,
+    }
+    let builder = Bounded::default().name("Alice").parent("Bob").value(42);
+    assert_eq!(builder.name, Some("Alice"));
+    assert_eq!(builder.parent, Some("Bob"));
+    assert_eq!(builder.value, Some(42));
+    assert_eq!(Bounded::<u32>::describe().len(), 3);
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder)]
+    struct Wrapper<T: Clone>
+    where
+        T: Send,
+    {
+        #[build_it(required)]
+        value: T,
+    }
+    let wrapper = Wrapper::builder().value("hi".to_string()).build().unwrap();
+    assert_eq!(wrapper.value, "hi");
+}
+
+#[test]
+fn const_generics() {
+    #[derive(Default, Builder)]
+    #[build_it(reset)]
+    struct Buf<const N: usize> {
+        data: Option<[u8; N]>,
+    }
+    let buf = Buf::default().data([1, 2, 3]);
+    assert_eq!(buf.data, Some([1, 2, 3]));
+
+    #[derive(Debug, Builder)]
+    #[build_it(typestate)]
+    struct Matrix<T, const N: usize> {
+        rows: [[T; N]; N],
+        name: Option<String>,
+    }
+    let matrix = Matrix::builder().rows([[1, 0], [0, 1]]).build();
+    assert_eq!(matrix.rows, [[1, 0], [0, 1]]);
+    assert_eq!(matrix.name, None);
+}
+
+#[test]
+fn default_type_params() {
+    #[derive(Default, Builder)]
+    struct Labeled<T = String> {
+        value: Option<T>,
+    }
+    let labeled: Labeled = Labeled::default().value("Alice".to_string());
+    assert_eq!(labeled.value, Some("Alice".to_string()));
+
+    #[derive(Debug, Builder)]
+    #[build_it(typestate)]
+    struct Grid<T = u8, const N: usize = 2> {
+        data: [T; N],
+        name: Option<String>,
+    }
+    let grid: Grid = Grid::builder().data([1, 2]).build();
+    assert_eq!(grid.data, [1, 2]);
+    assert_eq!(grid.name, None);
+}
+
+#[test]
+fn bound() {
+    fn label<T: std::fmt::Display>(value: T) -> String {
+        value.to_string()
+    }
+
+    // `spawn()` clones the builder, which only implements `Clone` if `T` does.
+    #[derive(Default, Clone, Builder)]
+    #[build_it(template, bound = "T: Clone")]
+    struct Entry<T> {
+        value: Option<T>,
+        #[build_it(with = "label", arg = "T", bound = "T: std::fmt::Display")]
+        label: Option<String>,
+    }
+
+    let template = Entry::default().value(42);
+    let entry = template.spawn().label(7);
+    assert_eq!(entry.value, Some(42));
+    assert_eq!(entry.label, Some("7".to_string()));
+}
+
 #[test]
 fn rename() {
     #[derive(Default, Builder)]
@@ -50,6 +169,26 @@ fn rename() {
 }
 
 #[test]
+fn alias() {
+    #[derive(Default, Builder)]
+    struct Client {
+        #[build_it(alias = "set_timeout", alias = "with_timeout")]
+        timeout: Option<u64>,
+        #[build_it(by_ref, rename = "retry_count", alias = "retries")]
+        retries: Option<u32>,
+    }
+
+    let mut client = Client::default().set_timeout(30);
+    assert_eq!(client.timeout, Some(30));
+    client = client.with_timeout(60);
+    assert_eq!(client.timeout, Some(60));
+    client.retries(3);
+    assert_eq!(client.retries, Some(3));
+}
+
+#[test]
+// `#[skip]` is deprecated in favor of `#[build_it(skip)]`, but still supported.
+#[allow(deprecated)]
 fn skip_fields() {
     #[derive(Default, Builder)]
     struct SkipFields {
@@ -84,6 +223,202 @@ fn doc_comments() {
     assert_eq!(builder.age, Some(30));
 }
 
+#[test]
+fn suffix() {
+    #[derive(Default, Builder)]
+    #[build_it(suffix = "_value")]
+    struct Gauge {
+        level: Option<u32>,
+        #[build_it(rename = "label")]
+        name: Option<String>,
+    }
+    impl Gauge {
+        // An existing method that would collide with the builder method.
+        fn level(&self) -> u32 {
+            self.level.unwrap_or_default()
+        }
+    }
+
+    let gauge = Gauge::default().level_value(3).label("cpu".to_string());
+    assert_eq!(gauge.level(), 3);
+    assert_eq!(gauge.name, Some("cpu".to_string()));
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder, suffix = "_value")]
+    struct Limit {
+        max: u32,
+    }
+
+    let limit = Limit::builder().max_value(10).build().unwrap();
+    assert_eq!(limit.max, 10);
+}
+
+#[test]
+fn rename_all() {
+    #[derive(Default, Builder)]
+    #[build_it(rename_all = "snake_case")]
+    #[allow(non_snake_case)]
+    struct Window {
+        maxWidth: Option<u32>,
+        HTTPProxy: Option<String>,
+        #[build_it(rename = "title")]
+        windowTitle: Option<String>,
+    }
+
+    let window = Window::default()
+        .max_width(800)
+        .http_proxy("proxy".to_string())
+        .title("main".to_string());
+    assert_eq!(window.maxWidth, Some(800));
+    assert_eq!(window.HTTPProxy, Some("proxy".to_string()));
+    assert_eq!(window.windowTitle, Some("main".to_string()));
+
+    #[derive(Default, Builder)]
+    #[build_it(rename_all = "camelCase", suffix = "Value")]
+    struct Counter {
+        start_value: Option<u32>,
+        #[build_it(maybe, flag)]
+        paused: Option<bool>,
+    }
+
+    let counter = Counter::default()
+        .startValueValue(1)
+        .maybePausedValue(None)
+        .enablePausedValue();
+    assert_eq!(counter.start_value, Some(1));
+    assert_eq!(counter.paused, Some(true));
+}
+
+#[test]
+fn vis() {
+    mod connection {
+        use build_it::Builder;
+
+        #[derive(Default, Builder)]
+        pub struct Connection {
+            pub host: Option<String>,
+            #[build_it(vis = "")]
+            pub retries: Option<u32>,
+        }
+
+        impl Connection {
+            pub fn reliable(self) -> Self {
+                self.retries(3)
+            }
+        }
+    }
+
+    let connection = connection::Connection::default()
+        .host("localhost".to_string())
+        .reliable();
+    assert_eq!(connection.host, Some("localhost".to_string()));
+    assert_eq!(connection.retries, Some(3));
+
+    mod request {
+        use build_it::Builder;
+
+        #[derive(Default, Builder)]
+        #[build_it(vis = "pub(super)")]
+        pub struct Request {
+            pub path: Option<String>,
+            #[build_it(vis = "pub")]
+            pub method: Option<String>,
+        }
+    }
+
+    let request = request::Request::default()
+        .path("/".to_string())
+        .method("GET".to_string());
+    assert_eq!(request.path, Some("/".to_string()));
+    assert_eq!(request.method, Some("GET".to_string()));
+}
+
+#[test]
+fn mutable() {
+    #[derive(Default, Builder)]
+    #[build_it(mutable)]
+    struct Query {
+        limit: Option<u32>,
+        #[build_it(each = "filter")]
+        filters: Option<Vec<String>>,
+    }
+
+    let mut query = Query::default();
+    query.limit(10);
+    for filter in ["active", "admin"] {
+        query.filter(filter.to_string());
+    }
+    assert_eq!(query.limit, Some(10));
+    assert_eq!(
+        query.filters,
+        Some(vec!["active".to_string(), "admin".to_string()])
+    );
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder, mutable)]
+    struct Page {
+        number: u32,
+    }
+
+    let mut builder = Page::builder();
+    builder.number(2);
+    assert_eq!(builder.build().unwrap().number, 2);
+}
+
+#[test]
+fn by_ref() {
+    #[derive(Default, Builder)]
+    struct Request {
+        url: Option<String>,
+        #[build_it(by_ref, each = "header")]
+        headers: Option<Vec<String>>,
+    }
+
+    let mut request = Request::default().url("/".to_string());
+    request.header("Accept".to_string());
+    assert_eq!(request.url, Some("/".to_string()));
+    assert_eq!(request.headers, Some(vec!["Accept".to_string()]));
+
+    #[derive(Default, Builder)]
+    #[build_it(mutable)]
+    struct Response {
+        status: Option<u16>,
+        #[build_it(by_value)]
+        body: Option<String>,
+    }
+
+    let mut response = Response::default().body("ok".to_string());
+    response.status(200);
+    assert_eq!(response.status, Some(200));
+    assert_eq!(response.body, Some("ok".to_string()));
+}
+
+#[test]
+fn dual() {
+    #[derive(Default, Builder)]
+    #[build_it(dual)]
+    struct Config {
+        #[build_it(into)]
+        name: Option<String>,
+        verbose: Option<bool>,
+    }
+
+    let mut config = Config::default().name("app");
+    config.set_verbose(true).set_name("other");
+    assert_eq!(config.name, Some("other".to_string()));
+    assert_eq!(config.verbose, Some(true));
+
+    #[derive(Default, Builder)]
+    struct Limits {
+        #[build_it(dual)]
+        max: Option<u32>,
+    }
+
+    let mut limits = Limits::default().max(1);
+    limits.set_max(2);
+    assert_eq!(limits.max, Some(2));
+}
+
 #[test]
 fn into() {
     #[derive(Default, Builder)]
@@ -96,4 +431,1643 @@ fn into() {
     let builder = Into::default().name("Alice").age(30u32);
     assert_eq!(builder.name, Some("Alice".to_string()));
     assert_eq!(builder.age, Some(30));
+
+    #[derive(Default, Builder)]
+    #[build_it(into)]
+    struct Container<T> {
+        label: Option<String>,
+        #[build_it(into = false)]
+        value: Option<T>,
+    }
+
+    // Without the opt-out, `T` couldn't be inferred from an `impl Into<T>` argument.
+    let container = Container::default().label("answer").value(42);
+    assert_eq!(container.label, Some("answer".to_string()));
+    assert_eq!(container.value, Some(42));
+}
+
+#[test]
+fn with_fn() {
+    fn normalize(name: String) -> String {
+        name.to_lowercase()
+    }
+    fn checksum(data: &[u8]) -> u32 {
+        data.iter().map(|&byte| u32::from(byte)).sum()
+    }
+
+    #[derive(Default, Builder)]
+    struct Upload {
+        #[build_it(with = "normalize")]
+        name: Option<String>,
+        #[build_it(rename = "data", with = "checksum", arg = "&[u8]")]
+        checksum: Option<u32>,
+    }
+
+    let upload = Upload::default()
+        .name("Report.PDF".to_string())
+        .data(&[1, 2, 3]);
+    assert_eq!(upload.name, Some("report.pdf".to_string()));
+    assert_eq!(upload.checksum, Some(6));
+}
+
+#[test]
+fn map() {
+    #[derive(Default, Builder)]
+    struct Form {
+        #[build_it(map = |s: &str| s.trim().to_owned())]
+        email: Option<String>,
+        #[build_it(required, map = |percent: u8| f64::from(percent) / 100.0)]
+        ratio: f64,
+    }
+
+    let form = Form::default().email("  alice@example.com ").ratio(25);
+    assert_eq!(form.email, Some("alice@example.com".to_string()));
+    assert_eq!(form.ratio, 0.25);
+}
+
+#[test]
+fn to_string() {
+    #[derive(Default, Builder)]
+    struct Label {
+        #[build_it(to_string)]
+        text: Option<String>,
+        #[build_it(to_string)]
+        id: Option<String>,
+    }
+
+    let label = Label::default().text("ready").id(42);
+    assert_eq!(label.text, Some("ready".to_string()));
+    assert_eq!(label.id, Some("42".to_string()));
+
+    let label = label.text(std::net::Ipv4Addr::LOCALHOST);
+    assert_eq!(label.text, Some("127.0.0.1".to_string()));
+}
+
+#[test]
+fn as_ref_path() {
+    use std::path::{Path, PathBuf};
+
+    #[derive(Default, Builder)]
+    struct Paths {
+        #[build_it(as_ref_path)]
+        config: Option<PathBuf>,
+    }
+
+    let paths = Paths::default().config("/etc/app.toml");
+    assert_eq!(paths.config, Some(PathBuf::from("/etc/app.toml")));
+    let paths = paths.config(Path::new("app.toml"));
+    assert_eq!(paths.config, Some(PathBuf::from("app.toml")));
+    let paths = paths.config(String::from("other.toml"));
+    assert_eq!(paths.config, Some(PathBuf::from("other.toml")));
+}
+
+#[test]
+fn as_ref_str() {
+    use std::rc::Rc;
+
+    #[derive(Default, Builder)]
+    struct User {
+        #[build_it(as_ref_str)]
+        name: Option<String>,
+    }
+
+    let user = User::default().name("Alice");
+    assert_eq!(user.name, Some("Alice".to_string()));
+    let name = "Bob".to_string();
+    let user = user.name(&name);
+    assert_eq!(user.name, Some("Bob".to_string()));
+    let user = user.name(Rc::<str>::from("Carol"));
+    assert_eq!(user.name, Some("Carol".to_string()));
+}
+
+#[test]
+fn clone() {
+    #[derive(Default, Builder)]
+    struct Request {
+        #[build_it(clone)]
+        headers: Option<Vec<String>>,
+    }
+
+    let headers = vec!["Accept: */*".to_string()];
+    let request = Request::default().headers(&headers);
+    assert_eq!(request.headers, Some(headers));
+}
+
+#[test]
+fn cow() {
+    use std::borrow::Cow;
+
+    #[derive(Default, Builder)]
+    struct Request<'a> {
+        path: Option<Cow<'a, str>>,
+    }
+
+    let owned = String::from("/owned");
+    let request = Request::default().path(owned.as_str());
+    assert_eq!(request.path, Some(Cow::Borrowed("/owned")));
+    let request = request.path(String::from("/other"));
+    assert_eq!(request.path, Some(Cow::<str>::Owned("/other".to_string())));
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder)]
+    struct Response<'a> {
+        body: Cow<'a, str>,
+    }
+
+    let response = Response::builder().body("ok").build().unwrap();
+    assert_eq!(response.body, "ok");
+}
+
+#[test]
+fn boxed() {
+    trait Shape {
+        fn area(&self) -> f64;
+    }
+    struct Square(f64);
+    impl Shape for Square {
+        fn area(&self) -> f64 {
+            self.0 * self.0
+        }
+    }
+
+    #[derive(Default, Builder)]
+    struct Canvas {
+        #[build_it(boxed)]
+        shape: Option<Box<dyn Shape + Send>>,
+        #[build_it(boxed)]
+        scale: Option<Box<f64>>,
+    }
+
+    let canvas = Canvas::default().shape(Square(2.0)).scale(0.5);
+    assert_eq!(canvas.shape.unwrap().area(), 4.0);
+    assert_eq!(canvas.scale, Some(Box::new(0.5)));
+}
+
+#[test]
+fn closures() {
+    #[derive(Default, Builder)]
+    struct Server {
+        handler: Option<Box<dyn Fn(u32) -> String + Send>>,
+        on_close: Option<Box<dyn FnMut()>>,
+    }
+
+    let prefix = "user".to_string();
+    let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+    let counter = calls.clone();
+    let mut server = Server::default()
+        .handler(move |id| format!("{}-{}", prefix, id))
+        .on_close(move || counter.set(counter.get() + 1));
+    assert_eq!((server.handler.unwrap())(7), "user-7");
+    (server.on_close.as_mut().unwrap())();
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn futures() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct NoopWaker;
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[derive(Default, Builder)]
+    struct Hooks {
+        on_start: Option<Pin<Box<dyn Future<Output = u32> + Send>>>,
+        #[build_it(boxed)]
+        on_stop: Option<Pin<Box<std::future::Ready<()>>>>,
+    }
+
+    let hooks = Hooks::default()
+        .on_start(async { 42 })
+        .on_stop(std::future::ready(()));
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut context = Context::from_waker(&waker);
+    let mut on_start = hooks.on_start.unwrap();
+    assert_eq!(on_start.as_mut().poll(&mut context), Poll::Ready(42));
+    assert!(hooks.on_stop.is_some());
+}
+
+#[test]
+fn arc_rc() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[derive(Default, Builder)]
+    struct Service {
+        #[build_it(arc)]
+        name: Option<Arc<String>>,
+        #[build_it(rc, into)]
+        label: Option<Rc<String>>,
+    }
+
+    let service = Service::default().name("api".to_string()).label("v1");
+    assert_eq!(service.name, Some(Arc::new("api".to_string())));
+    assert_eq!(service.label, Some(Rc::new("v1".to_string())));
+}
+
+#[test]
+fn required() {
+    #[derive(Default, Builder)]
+    #[build_it(describe)]
+    struct Listener {
+        #[build_it(required, into)]
+        address: String,
+        #[build_it(required)]
+        port: u16,
+        backlog: Option<u32>,
+    }
+
+    let listener = Listener::default().address("localhost").port(8080);
+    assert_eq!(listener.address, "localhost");
+    assert_eq!(listener.port, 8080);
+    assert_eq!(listener.backlog, None);
+    assert!(Listener::describe()[0].required);
+    assert!(Listener::describe()[1].required);
+    assert!(!Listener::describe()[2].required);
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder)]
+    struct Upstream {
+        #[build_it(required)]
+        timeout: Option<u64>,
+        retries: Option<u32>,
+    }
+
+    let upstream = Upstream::builder().timeout(30).build().unwrap();
+    assert_eq!(upstream.timeout, Some(30));
+    assert_eq!(upstream.retries, None);
+    assert!(matches!(
+        Upstream::builder().retries(3).build(),
+        Err(UpstreamBuilderError::MissingFields(fields)) if fields == ["timeout"]
+    ));
+
+    #[derive(Debug, Builder)]
+    #[build_it(typestate)]
+    struct Route {
+        #[build_it(required)]
+        path: Option<String>,
+    }
+
+    let route = Route::builder().path("/".to_string()).build();
+    assert_eq!(route.path, Some("/".to_string()));
+}
+
+#[test]
+fn default() {
+    #[derive(Debug, PartialEq, Builder)]
+    #[build_it(builder, describe)]
+    struct Pool {
+        #[build_it(default)]
+        size: usize,
+        #[build_it(default)]
+        idle_timeout: Option<u64>,
+        name: String,
+    }
+
+    let pool = Pool::builder().name("db".to_string()).build().unwrap();
+    assert_eq!(
+        pool,
+        Pool {
+            size: 0,
+            idle_timeout: Some(0),
+            name: "db".to_string(),
+        }
+    );
+    let pool = Pool::builder()
+        .name("db".to_string())
+        .size(4)
+        .build()
+        .unwrap();
+    assert_eq!(pool.size, 4);
+    assert_eq!(
+        PoolBuilder::describe()[0].default,
+        Some("Default::default()")
+    );
+    assert!(!PoolBuilder::describe()[0].required);
+    assert_eq!(PoolBuilder::describe()[2].default, None);
+    assert!(PoolBuilder::describe()[2].required);
+
+    #[derive(Debug, Builder)]
+    #[build_it(typestate)]
+    struct Retry {
+        #[build_it(default)]
+        attempts: u32,
+        backoff: u64,
+    }
+
+    let retry = Retry::builder().backoff(100).build();
+    assert_eq!(retry.attempts, 0);
+    let retry = Retry::builder().attempts(3).backoff(100).build();
+    assert_eq!(retry.attempts, 3);
+    assert_eq!(retry.backoff, 100);
+}
+
+#[test]
+fn default_fn() {
+    fn default_port() -> u16 {
+        8080
+    }
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder, describe)]
+    struct Listener {
+        #[build_it(default_fn = "default_port")]
+        port: u16,
+        #[build_it(default_fn = "String::new")]
+        host: Option<String>,
+    }
+
+    let listener = Listener::builder().build().unwrap();
+    assert_eq!(listener.port, 8080);
+    assert_eq!(listener.host, Some(String::new()));
+    let listener = Listener::builder().port(80).build().unwrap();
+    assert_eq!(listener.port, 80);
+    assert_eq!(
+        ListenerBuilder::describe()[0].default,
+        Some("default_port()")
+    );
+    assert_eq!(
+        ListenerBuilder::describe()[1].default,
+        Some("String::new()")
+    );
+}
+
+#[test]
+fn validate() {
+    fn check_port(port: &u16) -> Result<(), String> {
+        if *port < 1024 {
+            return Err(format!("port {} is reserved", port));
+        }
+        Ok(())
+    }
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder)]
+    struct Listener {
+        #[build_it(validate = "check_port")]
+        port: u16,
+        #[build_it(validate = "check_port")]
+        admin_port: Option<u16>,
+    }
+
+    let listener = Listener::builder().port(8080).build().unwrap();
+    assert_eq!(listener.port, 8080);
+    assert_eq!(listener.admin_port, None);
+
+    let err = Listener::builder()
+        .port(8080)
+        .admin_port(80)
+        .build()
+        .unwrap_err();
+    assert!(matches!(
+        &err,
+        ListenerBuilderError::InvalidField {
+            field: "admin_port",
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "invalid value for `admin_port`: port 80 is reserved"
+    );
+    let source = std::error::Error::source(&err).unwrap();
+    assert_eq!(source.to_string(), "port 80 is reserved");
+}
+
+#[test]
+fn validate_struct() {
+    #[derive(Debug, Builder)]
+    #[build_it(builder, validate = "Range::check")]
+    struct Range {
+        min: u32,
+        max: u32,
+    }
+    impl Range {
+        fn check(&self) -> Result<(), &'static str> {
+            if self.min > self.max {
+                return Err("`min` is greater than `max`");
+            }
+            Ok(())
+        }
+    }
+
+    let range = Range::builder().min(1).max(2).build().unwrap();
+    assert_eq!((range.min, range.max), (1, 2));
+
+    let err = Range::builder().min(3).max(2).build().unwrap_err();
+    assert!(matches!(err, RangeBuilderError::Invalid(_)));
+    assert_eq!(
+        err.to_string(),
+        "invalid `Range`: `min` is greater than `max`"
+    );
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[test]
+fn builder() {
+    #[derive(Debug, PartialEq)]
+    struct NotDefault(u32);
+
+    #[derive(Debug, PartialEq, Builder)]
+    #[build_it(builder)]
+    struct Server<'a> {
+        /// Address to listen on
+        #[build_it(into)]
+        address: String,
+        name: &'a str,
+        workers: Option<u32>,
+        handle: NotDefault,
+        #[build_it(skip)]
+        connections: Vec<u32>,
+    }
+
+    let server = Server::builder()
+        .address("localhost")
+        .name("api")
+        .handle(NotDefault(1))
+        .build()
+        .unwrap();
+    assert_eq!(
+        server,
+        Server {
+            address: "localhost".to_string(),
+            name: "api",
+            workers: None,
+            handle: NotDefault(1),
+            connections: vec![],
+        }
+    );
+
+    let server = ServerBuilder::default()
+        .address("localhost")
+        .name("api")
+        .workers(4)
+        .handle(NotDefault(2))
+        .build()
+        .unwrap();
+    assert_eq!(server.workers, Some(4));
+
+    let err = Server::builder().address("localhost").build().unwrap_err();
+    assert!(
+        matches!(&err, ServerBuilderError::MissingFields(fields) if fields == &["name", "handle"])
+    );
+    assert_eq!(err.to_string(), "`name`, `handle` are not set");
+    let err = Server::builder()
+        .address("localhost")
+        .handle(NotDefault(3))
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "`name` is not set");
+    let err: Box<dyn std::error::Error> = Box::new(err);
+    assert!(err.source().is_none());
+}
+
+// The builder compiles with `Result` shadowed and deprecated fields denied.
+#[deny(deprecated)]
+mod builder_hygiene {
+    use build_it::Builder;
+
+    #[allow(dead_code)]
+    type Result<T> = std::result::Result<T, ()>;
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder)]
+    pub struct Legacy {
+        #[build_it(required)]
+        pub name: String,
+        #[deprecated]
+        pub port: Option<u16>,
+    }
+
+    #[test]
+    fn build() {
+        let legacy = Legacy::builder().name("api".to_string()).build().unwrap();
+        assert_eq!(legacy.name, "api");
+        #[allow(deprecated)]
+        let port = legacy.port;
+        assert_eq!(port, None);
+    }
+}
+
+#[test]
+fn builder_skipped_generics() {
+    use std::marker::PhantomData;
+
+    // `T` is only used by a skipped field, so the builder has to use it itself.
+    #[derive(Debug, Builder)]
+    #[build_it(builder, into_parts)]
+    struct Handle<T> {
+        #[build_it(required)]
+        name: String,
+        #[build_it(skip)]
+        marker: PhantomData<T>,
+    }
+
+    let handle = Handle::<u32>::builder()
+        .name("jobs".to_string())
+        .build()
+        .unwrap();
+    assert_eq!(handle.name, "jobs");
+    assert_eq!(handle.marker, PhantomData);
+    let (name,) = Handle::<u32>::builder()
+        .name("jobs".to_string())
+        .into_parts();
+    assert_eq!(name, Some("jobs".to_string()));
+}
+
+#[test]
+fn typestate() {
+    #[derive(Debug, PartialEq, Builder)]
+    #[build_it(typestate)]
+    struct Server<'a, T> {
+        /// Address to listen on
+        #[build_it(into)]
+        address: String,
+        #[build_it(rename = "service")]
+        name: &'a str,
+        workers: Option<u32>,
+        state: T,
+        #[build_it(skip)]
+        connections: Vec<u32>,
+    }
+
+    // Required fields can be set in any order.
+    let server = Server::builder()
+        .state(1u8)
+        .workers(4)
+        .address("localhost")
+        .service("api")
+        .build();
+    assert_eq!(
+        server,
+        Server {
+            address: "localhost".to_string(),
+            name: "api",
+            workers: Some(4),
+            state: 1,
+            connections: vec![],
+        }
+    );
+
+    let server = Server::builder()
+        .service("api")
+        .address("localhost")
+        .state(())
+        .build();
+    assert_eq!(server.workers, None);
+}
+
+#[test]
+fn typestate_state_names() {
+    // `a_b` and `ab_` both map to the state `__AB`, which is also taken by the struct's generic.
+    #[allow(non_camel_case_types, non_snake_case)]
+    #[derive(Debug, PartialEq, Builder)]
+    #[build_it(typestate)]
+    struct Pair<__AB> {
+        a_b: u32,
+        ab_: u32,
+        value: __AB,
+    }
+
+    let pair = Pair::builder().ab_(2).value("x").a_b(1).build();
+    assert_eq!(
+        pair,
+        Pair {
+            a_b: 1,
+            ab_: 2,
+            value: "x",
+        }
+    );
+}
+
+#[test]
+fn presets() {
+    // clippy flags the repeated `workers` key across presets as a duplicated attribute.
+    #[allow(clippy::duplicated_attributes)]
+    #[derive(Default, Builder)]
+    #[build_it(
+        preset(name = "production", workers = 16, tls = true),
+        preset(name = "development", workers = 1, host = "localhost")
+    )]
+    struct Presets {
+        workers: Option<u32>,
+        tls: Option<bool>,
+        #[build_it(into)]
+        host: Option<String>,
+        #[build_it(skip)]
+        retries: u32,
+    }
+
+    let production = Presets::production();
+    assert_eq!(production.workers, Some(16));
+    assert_eq!(production.tls, Some(true));
+    assert_eq!(production.host, None);
+    assert_eq!(production.retries, 0);
+
+    let development = Presets::development().tls(false);
+    assert_eq!(development.workers, Some(1));
+    assert_eq!(development.tls, Some(false));
+    assert_eq!(development.host, Some("localhost".to_string()));
+}
+
+#[test]
+fn into_parts() {
+    #[derive(Default, Builder)]
+    #[build_it(into_parts)]
+    struct Connection {
+        host: Option<String>,
+        port: Option<u16>,
+        #[build_it(skip)]
+        retries: u32,
+    }
+
+    let (host, port, retries) = Connection::default()
+        .host("localhost".to_string())
+        .into_parts();
+    assert_eq!(host, Some("localhost".to_string()));
+    assert_eq!(port, None);
+    assert_eq!(retries, 0);
+}
+
+#[test]
+fn reset() {
+    #[derive(Default, Builder)]
+    #[build_it(reset)]
+    struct Download {
+        url: Option<String>,
+        resume: Option<bool>,
+        #[build_it(required)]
+        attempts: u32,
+        #[build_it(skip)]
+        id: Option<u64>,
+    }
+
+    let mut download = Download::default()
+        .url("https://example.com".to_string())
+        .resume(true)
+        .attempts(2);
+    download.id = Some(7);
+    download.reset();
+    assert_eq!(download.url, None);
+    assert_eq!(download.resume, None);
+    assert_eq!(download.attempts, 2);
+    assert_eq!(download.id, Some(7));
+}
+
+#[test]
+fn merge_from() {
+    #[derive(Default, Builder)]
+    #[build_it(merge_from)]
+    struct Settings {
+        host: Option<String>,
+        port: Option<u16>,
+        verbose: Option<bool>,
+    }
+
+    let defaults = Settings::default().host("localhost".to_string()).port(80);
+    let file = Settings::default().port(8080);
+    let cli = Settings::default().verbose(true);
+    let settings = defaults.merge_from(file).merge_from(cli);
+    assert_eq!(settings.host, Some("localhost".to_string()));
+    assert_eq!(settings.port, Some(8080));
+    assert_eq!(settings.verbose, Some(true));
+}
+
+#[test]
+fn cfg_fields() {
+    // `cfg(any())` is never enabled, and `cfg(test)` always is in tests.
+    #[derive(Default, Builder)]
+    #[build_it(reset, merge_from, counters)]
+    struct Server {
+        #[cfg(any())]
+        tls: Option<bool>,
+        #[cfg(test)]
+        port: Option<u16>,
+    }
+
+    let mut server = Server::default().merge_from(Server::default().port(443));
+    assert_eq!(server.port, Some(443));
+    assert_eq!(server.set_count(), 1);
+    server.reset();
+    assert!(server.is_empty());
+
+    #[derive(Debug, Builder)]
+    #[build_it(builder)]
+    struct Client {
+        #[cfg(any())]
+        #[build_it(required)]
+        proxy: String,
+        #[build_it(required)]
+        host: String,
+    }
+
+    let client = Client::builder()
+        .host("localhost".to_string())
+        .build()
+        .unwrap();
+    assert_eq!(client.host, "localhost");
+
+    // Enabled fields keep their `#[cfg]` attribute, which must not be rejected.
+    #[derive(Debug, Builder)]
+    #[build_it(typestate)]
+    struct Endpoint {
+        #[cfg(test)]
+        path: String,
+    }
+
+    let endpoint = Endpoint::builder().path("/".to_string()).build();
+    assert_eq!(endpoint.path, "/");
+
+    #[derive(Default, Builder)]
+    #[build_it(into_parts)]
+    struct Pair {
+        #[cfg(test)]
+        first: Option<u8>,
+        #[cfg(any())]
+        second: Option<u8>,
+    }
+
+    let (first,) = Pair::default().first(1).into_parts();
+    assert_eq!(first, Some(1));
+}
+
+#[test]
+fn from_str() {
+    #[derive(Default, Builder)]
+    #[build_it(from_str)]
+    struct Storage {
+        size: Option<u64>,
+        #[build_it(rename = "driver")]
+        backend: Option<String>,
+        readonly: Option<bool>,
+    }
+
+    let storage: Storage = "size=10, driver=overlay".parse().unwrap();
+    assert_eq!(storage.size, Some(10));
+    assert_eq!(storage.backend, Some("overlay".to_string()));
+    assert_eq!(storage.readonly, None);
+
+    let storage: Storage = "".parse().unwrap();
+    assert_eq!(storage.size, None);
+
+    assert_eq!(
+        "size=big".parse::<Storage>().err().unwrap(),
+        "invalid value for `size`: invalid digit found in string"
+    );
+    assert_eq!(
+        "backend=overlay".parse::<Storage>().err().unwrap(),
+        "unknown key `backend`"
+    );
+    assert_eq!(
+        "readonly".parse::<Storage>().err().unwrap(),
+        "expected `key=value`, found `readonly`"
+    );
+
+    // The values are passed to the setters, with their conversions.
+    #[derive(Default, Builder)]
+    #[build_it(from_str)]
+    struct Volume {
+        #[build_it(nullable)]
+        label: Option<Option<String>>,
+        #[build_it(arc)]
+        path: Option<std::sync::Arc<std::path::PathBuf>>,
+        #[build_it(map = |kib: u64| kib * 1024)]
+        bytes: Option<u64>,
+    }
+
+    let volume: Volume = "label=data,path=/mnt,bytes=2".parse().unwrap();
+    assert_eq!(volume.label, Some(Some("data".to_string())));
+    assert_eq!(
+        volume.path.as_deref(),
+        Some(&std::path::PathBuf::from("/mnt"))
+    );
+    assert_eq!(volume.bytes, Some(2048));
+
+    // In builder mode, `FromStr` builds the struct.
+    #[derive(Debug, Builder)]
+    #[build_it(builder, from_str)]
+    struct Mount {
+        #[build_it(required)]
+        target: String,
+        #[build_it(into)]
+        options: Option<String>,
+    }
+
+    let mount: Mount = "target=/mnt,options=ro".parse().unwrap();
+    assert_eq!(mount.target, "/mnt");
+    assert_eq!(mount.options, Some("ro".to_string()));
+    assert_eq!(
+        "options=ro".parse::<Mount>().unwrap_err(),
+        "`target` is not set"
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn set_json() {
+    #[derive(Default, Builder)]
+    #[build_it(set_json)]
+    struct Settings {
+        age: Option<u32>,
+        #[build_it(rename = "tags")]
+        labels: Option<Vec<String>>,
+        #[build_it(nullable)]
+        nickname: Option<Option<String>>,
+    }
+
+    let settings = Settings::default()
+        .set_json("age", serde_json::json!(42))
+        .unwrap()
+        .set_json("tags", serde_json::json!(["a", "b"]))
+        .unwrap()
+        .set_json("nickname", serde_json::Value::Null)
+        .unwrap();
+    assert_eq!(settings.age, Some(42));
+    assert_eq!(
+        settings.labels,
+        Some(vec!["a".to_string(), "b".to_string()])
+    );
+    assert_eq!(settings.nickname, Some(None));
+
+    let err = Settings::default()
+        .set_json("age", serde_json::json!("old"))
+        .err()
+        .unwrap();
+    assert!(
+        err.to_string().starts_with("invalid value for `age`: "),
+        "{}",
+        err
+    );
+    let err = Settings::default()
+        .set_json("labels", serde_json::json!([]))
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "unknown field `labels`");
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn to_json_partial() {
+    #[derive(Debug, Default, PartialEq, Builder, serde::Serialize, serde::Deserialize)]
+    #[build_it(to_json_partial)]
+    struct UserPatch {
+        #[serde(rename = "displayName")]
+        name: Option<String>,
+        age: Option<u32>,
+        #[build_it(sensitive)]
+        password: Option<String>,
+    }
+
+    let patch = UserPatch::default()
+        .name("Alice".to_string())
+        .password("hunter2".to_string());
+    let json = patch.to_json_partial();
+    assert_eq!(json, serde_json::json!({ "displayName": "Alice" }));
+    assert_eq!(
+        serde_json::from_value::<UserPatch>(json).unwrap(),
+        UserPatch::default().name("Alice".to_string())
+    );
+    assert_eq!(
+        UserPatch::default().to_json_partial(),
+        serde_json::json!({})
+    );
+}
+
+#[test]
+fn describe() {
+    #[derive(Default, Builder)]
+    #[build_it(describe)]
+    struct Describe<'a> {
+        /// Name of the person
+        /// spanning two lines.
+        ///
+        /// This paragraph is not part of the summary.
+        name: Option<&'a str>,
+        tags: Option<Vec<String>>,
+        #[build_it(skip)]
+        _internal: u32,
+    }
+
+    let fields = Describe::describe();
+    assert_eq!(fields.len(), 2);
+    assert_eq!(
+        fields[0],
+        DescribeFieldInfo {
+            name: "name",
+            ty: "&'a str",
+            doc: "Name of the person spanning two lines.",
+            required: false,
+            default: None,
+        }
+    );
+    assert_eq!(fields[1].name, "tags");
+    assert_eq!(fields[1].ty, "Vec<String>");
+    assert_eq!(fields[1].doc, "");
+
+    let builder = Describe::default().name("Alice").tags(vec![]);
+    assert_eq!(builder.name, Some("Alice"));
+    assert_eq!(builder.tags, Some(vec![]));
+}
+
+#[test]
+fn template() {
+    #[derive(Default, Clone, Builder)]
+    #[build_it(template)]
+    struct Request {
+        url: Option<String>,
+        headers: Option<Vec<(String, String)>>,
+    }
+
+    let template =
+        Request::default().headers(vec![("Accept".to_string(), "text/plain".to_string())]);
+    let first = template.spawn().url("/first".to_string());
+    let second = template.spawn().url("/second".to_string());
+
+    assert_eq!(template.url, None);
+    assert_eq!(first.url, Some("/first".to_string()));
+    assert_eq!(second.url, Some("/second".to_string()));
+    assert_eq!(first.headers, template.headers);
+    assert_eq!(second.headers, template.headers);
+}
+
+#[test]
+fn with() {
+    #[derive(Default, Builder)]
+    #[build_it(with)]
+    struct Server {
+        host: Option<String>,
+        port: Option<u16>,
+    }
+
+    let tls = true;
+    let server = Server::default().host("localhost".to_string()).with(|b| {
+        if tls {
+            b.port(443)
+        } else {
+            b.port(80)
+        }
+    });
+    assert_eq!(server.host, Some("localhost".to_string()));
+    assert_eq!(server.port, Some(443));
+
+    #[derive(Default, Builder)]
+    #[build_it(with, mutable)]
+    struct Client {
+        timeout: Option<u64>,
+    }
+
+    let mut client = Client::default();
+    client.with(|b| {
+        if tls {
+            b.timeout(30);
+        }
+    });
+    assert_eq!(client.timeout, Some(30));
+}
+
+#[test]
+fn counters() {
+    #[derive(Default, Builder)]
+    #[build_it(counters)]
+    struct Patch {
+        name: Option<String>,
+        age: Option<u32>,
+        #[build_it(skip)]
+        version: Option<u32>,
+    }
+
+    let patch = Patch {
+        version: Some(1),
+        ..Default::default()
+    };
+    assert!(patch.is_empty());
+    assert_eq!(patch.set_count(), 0);
+
+    let patch = patch.name("Alice".to_string());
+    assert!(!patch.is_empty());
+    assert_eq!(patch.set_count(), 1);
+
+    let patch = patch.age(30);
+    assert_eq!(patch.set_count(), 2);
+    assert_eq!(patch.name, Some("Alice".to_string()));
+    assert_eq!(patch.age, Some(30));
+    assert_eq!(patch.version, Some(1));
+}
+
+#[test]
+fn no_docs() {
+    #[derive(Default, Builder)]
+    #[build_it(describe)]
+    struct NoDocs {
+        /// Name of the person
+        name: Option<String>,
+        /// Implementation note: this is cached on first access.
+        #[build_it(no_docs)]
+        age: Option<u32>,
+    }
+
+    let builder = NoDocs::default().name("Alice".to_string()).age(30);
+    assert_eq!(builder.name, Some("Alice".to_string()));
+    assert_eq!(builder.age, Some(30));
+    assert_eq!(NoDocs::describe()[0].doc, "Name of the person");
+    assert_eq!(NoDocs::describe()[1].doc, "");
+}
+
+#[test]
+fn try_from() {
+    #[derive(Debug, PartialEq)]
+    enum Mode {
+        Read,
+        Write,
+    }
+    impl TryFrom<u8> for Mode {
+        type Error = String;
+        fn try_from(value: u8) -> Result<Self, Self::Error> {
+            match value {
+                0 => Ok(Mode::Read),
+                1 => Ok(Mode::Write),
+                _ => Err(format!("invalid mode {}", value)),
+            }
+        }
+    }
+
+    #[derive(Default, Builder)]
+    struct Packet {
+        #[build_it(try_from = "u8")]
+        mode: Option<Mode>,
+        #[build_it(rename = "len", try_from = "u64", try_from = "i32")]
+        length: Option<u32>,
+    }
+
+    let packet = Packet::default()
+        .mode_from_u8(1)
+        .unwrap()
+        .len_from_u64(42)
+        .unwrap();
+    assert_eq!(packet.mode, Some(Mode::Write));
+    assert_eq!(packet.length, Some(42));
+
+    let packet = packet.mode(Mode::Read).len_from_i32(7).unwrap();
+    assert_eq!(packet.mode, Some(Mode::Read));
+    assert_eq!(packet.length, Some(7));
+
+    assert_eq!(
+        Packet::default().mode_from_u8(2).err(),
+        Some("invalid mode 2".to_string())
+    );
+    assert!(Packet::default().len_from_i32(-1).is_err());
+}
+
+#[test]
+fn try_into() {
+    #[derive(Default, Builder)]
+    struct Packet {
+        #[build_it(try_into)]
+        length: Option<u32>,
+        #[build_it(required, try_into)]
+        ttl: u8,
+    }
+
+    let packet = Packet::default()
+        .try_length(42u64)
+        .unwrap()
+        .try_ttl(64i32)
+        .unwrap();
+    assert_eq!(packet.length, Some(42));
+    assert_eq!(packet.ttl, 64);
+
+    assert!(Packet::default().try_length(-1i64).is_err());
+    assert!(Packet::default().try_ttl(300u16).is_err());
+}
+
+#[test]
+fn bitflags() {
+    // A minimal stand-in for a type generated by the `bitflags!` macro.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Permissions(u8);
+    impl Permissions {
+        const READ: Self = Self(0b01);
+        const WRITE: Self = Self(0b10);
+        fn empty() -> Self {
+            Self(0)
+        }
+        fn insert(&mut self, other: Self) {
+            self.0 |= other.0;
+        }
+    }
+
+    #[derive(Default, Builder)]
+    struct File {
+        #[build_it(bitflags = "insert_permission")]
+        permissions: Option<Permissions>,
+    }
+
+    let file = File::default().insert_permission(Permissions::READ);
+    assert_eq!(file.permissions, Some(Permissions::READ));
+
+    let file = file.insert_permission(Permissions::WRITE);
+    assert_eq!(file.permissions, Some(Permissions(0b11)));
+
+    let file = file.permissions(Permissions::WRITE);
+    assert_eq!(file.permissions, Some(Permissions::WRITE));
+}
+
+#[test]
+fn add() {
+    #[derive(Default, Builder)]
+    struct Route {
+        path: Option<String>,
+        method: Option<String>,
+    }
+
+    #[derive(Default, Builder)]
+    struct Server {
+        #[build_it(add = "add_route")]
+        routes: Option<Vec<Route>>,
+    }
+
+    let server = Server::default()
+        .add_route(|route| route.path("/".to_string()))
+        .add_route(|route| route.path("/login".to_string()).method("POST".to_string()));
+    let routes = server.routes.unwrap();
+    assert_eq!(routes.len(), 2);
+    assert_eq!(routes[0].path, Some("/".to_string()));
+    assert_eq!(routes[0].method, None);
+    assert_eq!(routes[1].path, Some("/login".to_string()));
+    assert_eq!(routes[1].method, Some("POST".to_string()));
+}
+
+#[test]
+fn each() {
+    #[derive(Default, Builder)]
+    struct Command {
+        #[build_it(each = "arg", into)]
+        args: Option<Vec<String>>,
+        #[build_it(each = "env")]
+        envs: Option<Vec<(String, String)>>,
+    }
+
+    let command = Command::default()
+        .arg("-v")
+        .arg(String::from("--color"))
+        .env(("TERM".to_string(), "xterm".to_string()));
+    assert_eq!(
+        command.args,
+        Some(vec!["-v".to_string(), "--color".to_string()])
+    );
+    assert_eq!(
+        command.envs,
+        Some(vec![("TERM".to_string(), "xterm".to_string())])
+    );
+
+    let command = command.args(vec![]).arg("run");
+    assert_eq!(command.args, Some(vec!["run".to_string()]));
+}
+
+#[test]
+fn insert() {
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Default, Builder)]
+    struct Request {
+        #[build_it(insert = "header", into)]
+        headers: Option<HashMap<String, String>>,
+        #[build_it(insert)]
+        query: Option<BTreeMap<&'static str, u32>>,
+    }
+
+    let request = Request::default()
+        .header("Accept", "text/plain")
+        .insert_query("page", 2)
+        .insert_query("limit", 10);
+    assert_eq!(
+        request.headers,
+        Some(HashMap::from([(
+            "Accept".to_string(),
+            "text/plain".to_string()
+        )]))
+    );
+    assert_eq!(
+        request.query,
+        Some(BTreeMap::from([("limit", 10), ("page", 2)]))
+    );
+}
+
+#[test]
+fn extend() {
+    use std::collections::HashMap;
+
+    #[derive(Default, Builder)]
+    struct Config {
+        #[build_it(extend)]
+        include: Option<Vec<String>>,
+        #[build_it(extend)]
+        limits: Option<Vec<u32>>,
+        #[build_it(extend)]
+        labels: Option<HashMap<String, String>>,
+    }
+
+    let config = Config::default()
+        .include_extend(["src", "tests"].map(String::from))
+        .include_extend(vec!["benches".to_string()])
+        .limits_extend([1, 2].iter())
+        .limits_extend((3..5).map(|limit| limit * 10))
+        .labels_extend([("team".to_string(), "core".to_string())]);
+    assert_eq!(
+        config.include,
+        Some(vec![
+            "src".to_string(),
+            "tests".to_string(),
+            "benches".to_string()
+        ])
+    );
+    assert_eq!(config.limits, Some(vec![1, 2, 30, 40]));
+    assert_eq!(config.labels.unwrap()["team"], "core");
+}
+
+#[test]
+fn maybe() {
+    #[derive(Default, Builder)]
+    #[build_it(maybe)]
+    struct Cli {
+        #[build_it(into)]
+        config: Option<String>,
+        verbose: Option<bool>,
+        #[build_it(required)]
+        jobs: u32,
+    }
+
+    let cli = Cli::default()
+        .maybe_config(Some("build.toml".to_string()))
+        .maybe_verbose(None)
+        .jobs(4);
+    assert_eq!(cli.config, Some("build.toml".to_string()));
+    assert_eq!(cli.verbose, None);
+
+    let cli = cli.verbose(true).maybe_verbose(None).maybe_config(None);
+    assert_eq!(cli.verbose, None);
+    assert_eq!(cli.config, None);
+    assert_eq!(cli.jobs, 4);
+
+    #[derive(Default, Builder)]
+    struct Single {
+        #[build_it(maybe)]
+        name: Option<String>,
+    }
+
+    let single = Single::default().maybe_name(Some("Alice".to_string()));
+    assert_eq!(single.name, Some("Alice".to_string()));
+}
+
+#[test]
+fn nullable() {
+    #[derive(Default, Builder)]
+    struct Patch {
+        #[build_it(nullable, into)]
+        nickname: Option<Option<String>>,
+        email: Option<Option<String>>,
+    }
+
+    let patch = Patch::default();
+    assert_eq!(patch.nickname, None);
+    let patch = patch.nickname("Al").email(None);
+    assert_eq!(patch.nickname, Some(Some("Al".to_string())));
+    assert_eq!(patch.email, Some(None));
+    let patch = patch.null_nickname();
+    assert_eq!(patch.nickname, Some(None));
+}
+
+#[test]
+fn flag() {
+    #[derive(Default, Builder)]
+    struct Client {
+        #[build_it(flag)]
+        verbose: Option<bool>,
+        #[build_it(flag, rename = "redirects")]
+        follow_redirects: Option<bool>,
+    }
+
+    let client = Client::default().enable_verbose().disable_redirects();
+    assert_eq!(client.verbose, Some(true));
+    assert_eq!(client.follow_redirects, Some(false));
+
+    let client = client.disable_verbose().redirects(true);
+    assert_eq!(client.verbose, Some(false));
+    assert_eq!(client.follow_redirects, Some(true));
+}
+
+#[test]
+fn take_clear() {
+    #[derive(Default, Builder)]
+    #[build_it(take, clear)]
+    struct Job {
+        name: Option<String>,
+        retries: Option<u32>,
+        #[build_it(required)]
+        priority: u8,
+    }
+
+    let mut job = Job::default()
+        .name("backup".to_string())
+        .retries(3)
+        .priority(1);
+    assert_eq!(job.take_name(), Some("backup".to_string()));
+    assert_eq!(job.take_name(), None);
+    let job = job.clear_retries();
+    assert_eq!(job.retries, None);
+    assert_eq!(job.priority, 1);
+
+    #[derive(Default, Builder)]
+    struct Cache {
+        #[build_it(clear)]
+        size: Option<usize>,
+    }
+
+    let cache = Cache::default().size(10).clear_size();
+    assert_eq!(cache.size, None);
+}
+
+#[cfg(feature = "base64")]
+#[test]
+fn base64() {
+    #[derive(Default, Builder)]
+    struct Credentials {
+        #[build_it(base64)]
+        key: Option<Vec<u8>>,
+        #[build_it(required, base64)]
+        secret: Vec<u8>,
+    }
+
+    let credentials = Credentials::default()
+        .key_base64("aGVsbG8=")
+        .unwrap()
+        .secret_base64("d29ybGQ=")
+        .unwrap();
+    assert_eq!(credentials.key, Some(b"hello".to_vec()));
+    assert_eq!(credentials.secret, b"world".to_vec());
+    assert!(credentials.key_base64("not base64!").is_err());
+}
+
+#[cfg(feature = "hex")]
+#[test]
+fn hex() {
+    #[derive(Default, Builder)]
+    struct Credentials {
+        #[build_it(hex)]
+        key: Option<Vec<u8>>,
+    }
+
+    let credentials = Credentials::default().key_hex("68656c6c6f").unwrap();
+    assert_eq!(credentials.key, Some(b"hello".to_vec()));
+    assert!(credentials.key_hex("xyz").is_err());
+}
+
+#[test]
+fn duration() {
+    use std::time::Duration;
+
+    #[derive(Default, Builder)]
+    struct Client {
+        #[build_it(duration)]
+        timeout: Option<Duration>,
+        #[build_it(required, duration)]
+        interval: Duration,
+    }
+
+    let client = Client::default().timeout_secs(30).interval_millis(250);
+    assert_eq!(client.timeout, Some(Duration::from_secs(30)));
+    assert_eq!(client.interval, Duration::from_millis(250));
+    let client = client.timeout(Duration::from_nanos(1));
+    assert_eq!(client.timeout, Some(Duration::from_nanos(1)));
+}
+
+#[cfg(feature = "humantime")]
+#[test]
+fn humantime() {
+    use std::time::Duration;
+
+    #[derive(Default, Builder)]
+    struct Client {
+        #[build_it(humantime)]
+        timeout: Option<Duration>,
+        #[build_it(required, humantime)]
+        interval: Duration,
+    }
+
+    let client = Client::default()
+        .timeout_str("1h 15min")
+        .unwrap()
+        .interval_str("250ms")
+        .unwrap();
+    assert_eq!(client.timeout, Some(Duration::from_secs(75 * 60)));
+    assert_eq!(client.interval, Duration::from_millis(250));
+    assert!(client.timeout_str("soon").is_err());
+}
+
+#[test]
+fn fmt() {
+    #[derive(Default, Builder)]
+    struct Event {
+        #[build_it(fmt)]
+        message: Option<String>,
+    }
+
+    let id = 42;
+    let event = Event::default().message_fmt(format_args!("job {id} finished"));
+    assert_eq!(event.message, Some("job 42 finished".to_string()));
+
+    let event = event.message("done".to_string());
+    assert_eq!(event.message, Some("done".to_string()));
+}
+
+#[test]
+fn shared() {
+    #[derive(Default, Clone, Builder)]
+    #[build_it(shared)]
+    struct Job {
+        name: Option<String>,
+        #[build_it(into)]
+        retries: Option<u32>,
+    }
+
+    let shared = SharedJobBuilder::new(Job::default());
+    let handle = shared.clone();
+    std::thread::spawn(move || {
+        handle.name("backup".to_string());
+    })
+    .join()
+    .unwrap();
+    shared.retries(3u8);
+
+    let job = shared.build();
+    assert_eq!(job.name, Some("backup".to_string()));
+    assert_eq!(job.retries, Some(3));
+
+    // In builder mode, the handle wraps the builder and `build()` builds the struct.
+    #[derive(Debug, Builder)]
+    #[build_it(builder, shared)]
+    struct Task {
+        #[build_it(required)]
+        name: String,
+        retries: Option<u32>,
+    }
+
+    let shared = SharedTaskBuilder::new(Task::builder());
+    shared.retries(3);
+    assert!(shared.build().is_err());
+    shared.name("backup".to_string());
+    let task = shared.build().unwrap();
+    assert_eq!(task.name, "backup");
+    assert_eq!(task.retries, Some(3));
+
+    // The handle's setters take the same arguments as the builder's.
+    #[derive(Default, Clone, Builder)]
+    #[build_it(shared)]
+    struct Patch {
+        #[build_it(nullable)]
+        nickname: Option<Option<String>>,
+        #[build_it(nullable)]
+        email: Option<Option<String>>,
+        #[build_it(arc)]
+        config: Option<std::sync::Arc<String>>,
+    }
+
+    let shared = SharedPatchBuilder::new(Patch::default());
+    shared
+        .nickname("y".to_string())
+        .null_email()
+        .config("prod".to_string());
+    let patch = shared.build();
+    assert_eq!(patch.nickname, Some(Some("y".to_string())));
+    assert_eq!(patch.email, Some(None));
+    assert_eq!(patch.config.as_deref().map(String::as_str), Some("prod"));
+}
+
+#[test]
+fn test_setter() {
+    #[derive(Default, Builder)]
+    struct Scheduler {
+        #[build_it(skip, test_setter)]
+        now: u64,
+        #[build_it(skip, test_setter)]
+        seed: Option<u64>,
+        interval: Option<u64>,
+    }
+
+    let scheduler = Scheduler::default().now(1_000).seed(7).interval(60);
+    assert_eq!(scheduler.now, 1_000);
+    assert_eq!(scheduler.seed, Some(7));
+    assert_eq!(scheduler.interval, Some(60));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics() {
+    use metrics::{Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder};
+    use metrics::{SharedString, Unit};
+    use std::sync::{Arc, Mutex};
+
+    // Records the field label of every counter increment.
+    #[derive(Default)]
+    struct Calls(Arc<Mutex<Vec<String>>>);
+
+    struct FieldCounter {
+        field: String,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl CounterFn for FieldCounter {
+        fn increment(&self, value: u64) {
+            for _ in 0..value {
+                self.calls.lock().unwrap().push(self.field.clone());
+            }
+        }
+
+        fn absolute(&self, _value: u64) {}
+    }
+
+    impl Recorder for Calls {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            assert_eq!(key.name(), "build_it_setter_calls");
+            let field = key
+                .labels()
+                .find(|label| label.key() == "field")
+                .unwrap()
+                .value()
+                .to_string();
+            Counter::from_arc(Arc::new(FieldCounter {
+                field,
+                calls: Arc::clone(&self.0),
+            }))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[derive(Default, Builder)]
+    #[build_it(metrics)]
+    struct ServerConfig {
+        workers: Option<u32>,
+        #[build_it(rename = "port_number")]
+        port: Option<u16>,
+    }
+
+    let recorder = Calls::default();
+    let config = metrics::with_local_recorder(&recorder, || {
+        ServerConfig::default()
+            .workers(4)
+            .port_number(80)
+            .workers(8)
+    });
+    assert_eq!(config.workers, Some(8));
+    assert_eq!(*recorder.0.lock().unwrap(), ["workers", "port", "workers"]);
+}
+
+#[cfg(feature = "clap")]
+#[test]
+fn clap() {
+    use clap::Parser;
+
+    #[derive(Debug, Default, Builder)]
+    #[build_it(clap)]
+    struct ServerConfig {
+        /// Number of worker threads
+        workers: Option<u32>,
+        max_connections: Option<usize>,
+        #[build_it(required)]
+        host: String,
+    }
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        config: ServerConfigArgs,
+    }
+
+    let cli = Cli::try_parse_from(["server", "--workers", "4", "--host", "localhost"]).unwrap();
+    let config: ServerConfig = cli.config.into();
+    assert_eq!(config.workers, Some(4));
+    assert_eq!(config.max_connections, None);
+    assert_eq!(config.host, "localhost");
+
+    let cli = Cli::try_parse_from(["server", "--max-connections", "100"]).unwrap();
+    let config = cli.config.apply(ServerConfig::default().workers(2));
+    assert_eq!(config.workers, Some(2));
+    assert_eq!(config.max_connections, Some(100));
+
+    assert!(Cli::try_parse_from(["server", "--workers", "many"]).is_err());
 }